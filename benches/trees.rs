@@ -57,65 +57,65 @@ macro_rules! bench_trees {
 }
 
 #[bench]
-fn bench_sink_box(b: &mut test::Bencher) -> () {
+fn bench_sink_box(b: &mut test::Bencher) {
     bench_trees!(b, io::sink(), BoxAllocator, 1)
 }
 
 #[bench]
-fn bench_sink_arena(b: &mut test::Bencher) -> () {
+fn bench_sink_arena(b: &mut test::Bencher) {
     bench_trees!(b, io::sink(), Arena::new(), 1)
 }
 
 #[bench]
-fn bench_vec_box(b: &mut test::Bencher) -> () {
+fn bench_vec_box(b: &mut test::Bencher) {
     bench_trees!(b, Vec::new(), BoxAllocator, 1)
 }
 
 #[bench]
-fn bench_vec_arena(b: &mut test::Bencher) -> () {
+fn bench_vec_arena(b: &mut test::Bencher) {
     bench_trees!(b, Vec::new(), Arena::new(), 1)
 }
 
 #[bench]
-fn bench_io_box(b: &mut test::Bencher) -> () {
+fn bench_io_box(b: &mut test::Bencher) {
     let out = tempfile::tempfile().unwrap();
     bench_trees!(b, io::BufWriter::new(out), BoxAllocator, 1)
 }
 
 #[bench]
-fn bench_io_arena(b: &mut test::Bencher) -> () {
+fn bench_io_arena(b: &mut test::Bencher) {
     let out = tempfile::tempfile().unwrap();
     bench_trees!(b, io::BufWriter::new(out), Arena::new(), 1)
 }
 
 #[bench]
-fn bench_large_sink_box(b: &mut test::Bencher) -> () {
+fn bench_large_sink_box(b: &mut test::Bencher) {
     bench_trees!(b, io::sink(), BoxAllocator, 50)
 }
 
 #[bench]
-fn bench_large_sink_arena(b: &mut test::Bencher) -> () {
+fn bench_large_sink_arena(b: &mut test::Bencher) {
     bench_trees!(b, io::sink(), Arena::new(), 50)
 }
 
 #[bench]
-fn bench_large_vec_box(b: &mut test::Bencher) -> () {
+fn bench_large_vec_box(b: &mut test::Bencher) {
     bench_trees!(b, Vec::new(), BoxAllocator, 50)
 }
 
 #[bench]
-fn bench_large_vec_arena(b: &mut test::Bencher) -> () {
+fn bench_large_vec_arena(b: &mut test::Bencher) {
     bench_trees!(b, Vec::new(), Arena::new(), 50)
 }
 
 #[bench]
-fn bench_large_io_box(b: &mut test::Bencher) -> () {
+fn bench_large_io_box(b: &mut test::Bencher) {
     let out = tempfile::tempfile().unwrap();
     bench_trees!(b, io::BufWriter::new(out), BoxAllocator, 50)
 }
 
 #[bench]
-fn bench_large_io_arena(b: &mut test::Bencher) -> () {
+fn bench_large_io_arena(b: &mut test::Bencher) {
     let out = tempfile::tempfile().unwrap();
     bench_trees!(b, io::BufWriter::new(out), Arena::new(), 50)
 }
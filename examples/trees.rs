@@ -8,7 +8,7 @@ use std::str;
 pub struct Forest<'a>(&'a [Tree<'a>]);
 
 impl<'a> Forest<'a> {
-    fn forest(forest: &'a [Tree<'a>]) -> Forest<'a> {
+    fn new(forest: &'a [Tree<'a>]) -> Forest<'a> {
         Forest(forest)
     }
 
@@ -22,7 +22,7 @@ impl<'a> Forest<'a> {
         D::Doc: Clone,
         A: Clone,
     {
-        if (self.0).len() == 0 {
+        if self.0.is_empty() {
             allocator.nil()
         } else {
             allocator
@@ -42,7 +42,7 @@ impl<'a> Forest<'a> {
         let forest = self.0;
         let separator = allocator.text(",").append(allocator.newline());
         allocator.intersperse(
-            forest.into_iter().map(|tree| tree.pretty(allocator)),
+            forest.iter().map(|tree| tree.pretty(allocator)),
             separator,
         )
     }
@@ -65,7 +65,7 @@ impl<'a> Tree<'a> {
     pub fn node_with_forest(node: &str, forest: &'a [Tree<'a>]) -> Tree<'a> {
         Tree {
             node: node.to_string(),
-            forest: Forest::forest(forest),
+            forest: Forest::new(forest),
         }
     }
 
@@ -0,0 +1,207 @@
+//! Derive macro for the [`Pretty`](https://docs.rs/pretty/*/pretty/value/trait.Pretty.html) trait.
+//!
+//! The derived implementation lays values out like their `Debug` output, such as
+//! `Name { field: value }` for structs and `Variant(value)` for tuple variants, grouping and
+//! nesting each value so that nested records are broken over several lines only when they do not
+//! fit the width they are rendered at.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate pretty_derive;
+//! extern crate pretty;
+//!
+//! use pretty::value::Pretty;
+//! use pretty::{BoxAllocator, BoxDoc, Doc};
+//!
+//! #[derive(Pretty)]
+//! struct Point {
+//!     x: i32,
+//!     #[pretty(rename = "y-coordinate")]
+//!     y: i32,
+//!     #[pretty(skip)]
+//!     cache: Vec<u8>,
+//! }
+//!
+//! # fn main() {
+//! let point = Point { x: 1, y: 2, cache: vec![] };
+//! let doc: Doc<BoxDoc<()>> = point.pretty(&BoxAllocator).into();
+//! assert_eq!(doc.pretty(80).to_string(), "Point { x: 1, y-coordinate: 2 }");
+//! # }
+//! ```
+//!
+//! The following attributes are supported:
+//!
+//! * `#[pretty(rename = "name")]` on a type, variant or field changes the name that is printed.
+//! * `#[pretty(skip)]` on a field leaves the field out of the output.
+//! * `#[pretty(with = "path")]` on a field formats the field with the function at `path`, which
+//!   is called as `path(&field, allocator)` and must return a `DocBuilder`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericParam, Ident, Lifetime, LifetimeDef, Lit,
+    Meta, NestedMeta, Path, Result,
+};
+
+#[proc_macro_derive(Pretty, attributes(pretty))]
+pub fn derive_pretty(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    skip: bool,
+    with: Option<Path>,
+}
+
+impl Options {
+    fn from_attributes(attrs: &[Attribute]) -> Result<Options> {
+        let mut options = Options::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pretty")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "expected `#[pretty(...)]`")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") => {
+                        options.skip = true;
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("rename") => {
+                        match pair.lit {
+                            Lit::Str(ref name) => options.rename = Some(name.value()),
+                            ref lit => return Err(Error::new(lit.span(), "expected a string")),
+                        }
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("with") => {
+                        match pair.lit {
+                            Lit::Str(ref path) => options.with = Some(path.parse()?),
+                            ref lit => return Err(Error::new(lit.span(), "expected a string")),
+                        }
+                    }
+                    nested => {
+                        return Err(Error::new(
+                            nested.span(),
+                            "unknown attribute, expected `rename`, `skip` or `with`",
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    fn name(&self, ident: &Ident) -> String {
+        self.rename.clone().unwrap_or_else(|| ident.to_string())
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attributes(&input.attrs)?;
+    let ident = &input.ident;
+
+    let body = match input.data {
+        Data::Struct(ref data) => {
+            let name = options.name(ident);
+            let arm = expand_fields(quote!(#ident), &name, &data.fields)?;
+            quote!(match *self { #arm })
+        }
+        Data::Enum(ref data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let name = Options::from_attributes(&variant.attrs)?.name(&variant.ident);
+                let variant_ident = &variant.ident;
+                arms.push(expand_fields(
+                    quote!(#ident::#variant_ident),
+                    &name,
+                    &variant.fields,
+                )?);
+            }
+            quote!(match *self { #(#arms)* })
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`Pretty` can not be derived for unions",
+            ))
+        }
+    };
+
+    let lifetime = Lifetime::new("'__a", Span::call_site());
+    let mut generics = input.generics.clone();
+    let params = input.generics.type_params().map(|param| &param.ident);
+    let predicates = quote!(#(#params: ::pretty::value::Pretty<#lifetime, __D, __A>,)*);
+    generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())),
+    );
+    generics.params.push(parse_quote!(__D));
+    generics.params.push(parse_quote!(__A));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_predicates = where_clause.map(|clause| &clause.predicates);
+
+    Ok(quote! {
+        impl #impl_generics ::pretty::value::Pretty<#lifetime, __D, __A> for #ident #ty_generics
+        where
+            __D: ?Sized + ::pretty::DocAllocator<#lifetime, __A>,
+            #predicates
+            #where_predicates
+        {
+            fn pretty(&self, allocator: &#lifetime __D) -> ::pretty::DocBuilder<#lifetime, __D, __A> {
+                #body
+            }
+        }
+    })
+}
+
+/// Expands to a match arm which lays out the fields of a struct or variant.
+fn expand_fields(path: TokenStream2, name: &str, fields: &Fields) -> Result<TokenStream2> {
+    let mut bindings = Vec::new();
+    let mut docs = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let options = Options::from_attributes(&field.attrs)?;
+        if options.skip {
+            continue;
+        }
+        let binding = Ident::new(&format!("__field{}", i), Span::call_site());
+        let doc = match options.with {
+            Some(ref with) => quote!(#with(#binding, allocator)),
+            None => quote!(::pretty::value::Pretty::pretty(#binding, allocator)),
+        };
+        match field.ident {
+            Some(ref ident) => {
+                let field_name = options.name(ident);
+                bindings.push(quote!(#ident: ref #binding));
+                docs.push(quote!((#field_name, #doc)));
+            }
+            None => {
+                let index = syn::Index::from(i);
+                bindings.push(quote!(#index: ref #binding));
+                docs.push(doc);
+            }
+        }
+    }
+
+    let doc = if docs.is_empty() {
+        quote!(::pretty::DocAllocator::text(allocator, #name))
+    } else {
+        match *fields {
+            Fields::Named(_) => quote!(::pretty::value::record(allocator, #name, vec![#(#docs),*])),
+            _ => quote!(::pretty::value::tuple(allocator, #name, vec![#(#docs),*])),
+        }
+    };
+    Ok(quote!(#path { #(#bindings,)* .. } => #doc,))
+}
@@ -0,0 +1,113 @@
+extern crate pretty;
+#[macro_use]
+extern crate pretty_derive;
+
+use pretty::value::Pretty;
+use pretty::{BoxAllocator, BoxDoc, Doc, DocAllocator, DocBuilder};
+
+fn render<T: for<'a> Pretty<'a, BoxAllocator>>(value: &T, width: usize) -> String {
+    let doc: Doc<BoxDoc<()>> = value.pretty(&BoxAllocator).into();
+    doc.pretty(width).to_string()
+}
+
+#[derive(Pretty)]
+struct Unit;
+
+#[derive(Pretty)]
+struct Wrapper(i32, String);
+
+#[derive(Pretty)]
+#[pretty(rename = "Pair")]
+struct Generic<T> {
+    first: T,
+    second: Option<T>,
+}
+
+#[derive(Pretty)]
+enum Shape {
+    Empty,
+    Circle { radius: u32 },
+    Polygon(Vec<(i32, i32)>),
+}
+
+fn hex<'a, D, A>(value: &u32, allocator: &'a D) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    allocator.text(format!("{:#x}", value))
+}
+
+#[derive(Pretty)]
+struct Options {
+    #[pretty(with = "hex")]
+    color: u32,
+    #[pretty(skip)]
+    #[allow(dead_code)]
+    cache: Vec<u8>,
+    #[pretty(rename = "type")]
+    kind: Shape,
+}
+
+#[test]
+fn structs() {
+    assert_eq!(render(&Unit, 80), "Unit");
+    assert_eq!(render(&Wrapper(1, "a".into()), 80), r#"Wrapper(1, "a")"#);
+    assert_eq!(
+        render(
+            &Generic {
+                first: 1,
+                second: Some(2),
+            },
+            80
+        ),
+        "Pair { first: 1, second: Some(2) }"
+    );
+}
+
+#[test]
+fn enums() {
+    assert_eq!(render(&Shape::Empty, 80), "Empty");
+    assert_eq!(
+        render(&Shape::Circle { radius: 3 }, 80),
+        "Circle { radius: 3 }"
+    );
+    assert_eq!(
+        render(&Shape::Polygon(vec![(0, 0), (1, 1)]), 80),
+        "Polygon([(0, 0), (1, 1)])"
+    );
+}
+
+#[test]
+fn attributes_and_nesting() {
+    let options = Options {
+        color: 255,
+        cache: vec![1, 2, 3],
+        kind: Shape::Polygon(vec![(0, 0), (10, 0), (10, 10)]),
+    };
+    assert_eq!(
+        render(&options, 80),
+        "Options { color: 0xff, type: Polygon([(0, 0), (10, 0), (10, 10)]) }"
+    );
+    assert_eq!(
+        render(&options, 50),
+        "\
+Options {
+    color: 0xff,
+    type: Polygon([(0, 0), (10, 0), (10, 10)]),
+}"
+    );
+    assert_eq!(
+        render(&options, 30),
+        "\
+Options {
+    color: 0xff,
+    type: Polygon(
+        [
+            (0, 0),
+            (10, 0),
+            (10, 10),
+        ],
+    ),
+}"
+    );
+}
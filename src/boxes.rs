@@ -0,0 +1,217 @@
+//! Two dimensional composition of rendered documents.
+//!
+//! A `Block` holds the lines of a document rendered at a given width, together with their
+//! annotations. Blocks can be put next to each other, for instance for a side by side diff, or
+//! above each other, and the result turned back into a document or written out directly.
+//!
+//! ```
+//! use pretty::boxes::{Block, Vertical};
+//! use pretty::{Doc, BoxDoc};
+//!
+//! let words = |text: &'static str| Block::new(&Doc::<BoxDoc<()>>::reflow(text), 12);
+//! let old = words("The quick brown fox jumps over the lazy dog");
+//! let new = words("The quick red fox jumps");
+//! let diff = Block::beside(vec![old, new], " | ", Vertical::Top);
+//!
+//! assert_eq!(
+//!     diff.to_string(),
+//!     "The quick    | The quick
+//! brown fox    | red fox
+//! jumps over   | jumps
+//! the lazy dog"
+//! );
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+
+use render::text_width;
+use table::Alignment;
+use {Doc, DocAllocator, DocBuilder, Line, LineBuffer};
+
+/// How blocks of different heights are placed next to each other.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Vertical {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// Rendered lines with the width of the widest one, in characters.
+///
+/// Lines are stored without the padding which would extend them to the width of the block, so
+/// composed blocks do not end in trailing whitespace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block<A = ()> {
+    lines: Vec<Line<A>>,
+    width: usize,
+}
+
+impl<A> Block<A>
+where
+    A: Clone,
+{
+    /// Renders `doc` at `width`.
+    pub fn new<'b, T>(doc: &'b Doc<'b, T, A>, width: usize) -> Block<A>
+    where
+        T: Deref<Target = Doc<'b, T, A>>,
+    {
+        let mut out = LineBuffer::new();
+        doc.render_raw(width, &mut out)
+            .expect("writing to a line buffer can not fail");
+        Block::from_lines(out.into_lines())
+    }
+
+    /// A block of the lines of `text`, without annotations.
+    pub fn text(text: &str) -> Block<A> {
+        Block::from_lines(
+            text.lines()
+                .map(|line| Line {
+                    spans: vec![(line.to_string(), None)],
+                })
+                .collect(),
+        )
+    }
+
+    /// A block of `height` empty lines which is `width` characters wide, for spacing other blocks.
+    pub fn empty(width: usize, height: usize) -> Block<A> {
+        Block {
+            lines: vec![Line { spans: Vec::new() }; height],
+            width,
+        }
+    }
+
+    fn from_lines(lines: Vec<Line<A>>) -> Block<A> {
+        let width = lines.iter().map(line_width).max().unwrap_or(0);
+        Block { lines, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn lines(&self) -> &[Line<A>] {
+        &self.lines
+    }
+
+    /// Places `blocks` next to each other, separated by `separator` on each line. Blocks lower
+    /// than the highest one are placed according to `vertical`.
+    ///
+    /// Each line ends after the last block which has text on it.
+    pub fn beside(blocks: Vec<Block<A>>, separator: &str, vertical: Vertical) -> Block<A> {
+        let height = blocks.iter().map(Block::height).max().unwrap_or(0);
+        let separator_width = text_width(separator);
+        let width = blocks.iter().map(|block| block.width).sum::<usize>()
+            + separator_width * blocks.len().saturating_sub(1);
+
+        let mut lines = vec![Line { spans: Vec::new() }; height];
+        for (i, line) in lines.iter_mut().enumerate() {
+            let cells = blocks
+                .iter()
+                .map(|block| {
+                    let top = match vertical {
+                        Vertical::Top => 0,
+                        Vertical::Middle => (height - block.height()) / 2,
+                        Vertical::Bottom => height - block.height(),
+                    };
+                    i.checked_sub(top).and_then(|i| block.lines.get(i))
+                })
+                .collect::<Vec<_>>();
+            let last = match cells
+                .iter()
+                .rposition(|cell| cell.map_or(false, |cell| line_width(cell) != 0))
+            {
+                Some(last) => last,
+                None => continue,
+            };
+            for (j, cell) in cells.into_iter().enumerate().take(last + 1) {
+                if j != 0 {
+                    line.spans.push((separator.to_string(), None));
+                }
+                if let Some(cell) = cell {
+                    line.spans.extend(cell.spans.iter().cloned());
+                }
+                if j != last {
+                    push_spaces(line, blocks[j].width - cell.map_or(0, line_width));
+                }
+            }
+        }
+        Block { lines, width }
+    }
+
+    /// Places `blocks` above each other. Blocks narrower than the widest one are placed according
+    /// to `alignment`.
+    pub fn above(blocks: Vec<Block<A>>, alignment: Alignment) -> Block<A> {
+        let width = blocks.iter().map(|block| block.width).max().unwrap_or(0);
+        let mut lines = Vec::new();
+        for block in blocks {
+            let padding = width - block.width;
+            let indent = match alignment {
+                Alignment::Left => 0,
+                Alignment::Right => padding,
+                Alignment::Center => padding / 2,
+            };
+            for line in block.lines {
+                let mut padded = Line { spans: Vec::new() };
+                if !line.spans.is_empty() {
+                    push_spaces(&mut padded, indent);
+                    padded.spans.extend(line.spans);
+                }
+                lines.push(padded);
+            }
+        }
+        Block { lines, width }
+    }
+
+    /// Converts the block into a document with the lines of the block, annotated as they were.
+    ///
+    /// The lines after the first one start at the indentation the document is placed at.
+    pub fn to_doc<'a, D>(&self, allocator: &'a D) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let mut doc = allocator.nil();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i != 0 {
+                doc = doc.append(allocator.newline());
+            }
+            for (text, annotation) in &line.spans {
+                let text = allocator.text(text.clone());
+                doc = doc.append(match *annotation {
+                    Some(ref annotation) => text.annotate(annotation.clone()),
+                    None => text,
+                });
+            }
+        }
+        doc
+    }
+}
+
+/// Writes the lines of the block without annotations.
+impl<A> fmt::Display for Block<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+            for (text, _) in &line.spans {
+                f.write_str(text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn line_width<A>(line: &Line<A>) -> usize {
+    line.spans.iter().map(|span| text_width(&span.0)).sum()
+}
+
+fn push_spaces<A>(line: &mut Line<A>, spaces: usize) {
+    if spaces != 0 {
+        line.spans.push((" ".repeat(spaces), None));
+    }
+}
@@ -0,0 +1,271 @@
+//! Width aware formatting of `Debug` output.
+//!
+//! The output of `{:?}` is parsed for its brackets, commas and string literals and turned into a
+//! document, so values of any type implementing `Debug` can be laid out at a given width without
+//! implementing [`Pretty`](../value/trait.Pretty.html) for them.
+//!
+//! ```
+//! use pretty::debug;
+//!
+//! #[derive(Debug)]
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! let points = vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
+//! assert_eq!(
+//!     debug::pretty_debug(&points, 80).to_string(),
+//!     "[Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]"
+//! );
+//! assert_eq!(
+//!     debug::pretty_debug(&points, 30).to_string(),
+//!     "\
+//! [
+//!     Point { x: 1, y: 2 },
+//!     Point { x: 3, y: 4 },
+//! ]"
+//! );
+//! ```
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use value;
+use {BoxAllocator, Doc, DocAllocator, DocBuilder};
+
+/// Returns the `Debug` output of `value` as a document.
+pub fn to_doc<'a, D, A, T>(allocator: &'a D, value: &T) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: ?Sized + fmt::Debug,
+{
+    let source = format!("{:?}", value);
+    let mut parser = Parser {
+        source: &source,
+        chars: source.char_indices().peekable(),
+    };
+    let (items, _) = parser.items(None);
+    // Items at the top level are separated like the elements of a list, without its delimiters
+    let mut doc = allocator.nil();
+    for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+            doc = doc.append(allocator.text(",")).append(allocator.space());
+        }
+        doc = doc.append(layout_item(allocator, item));
+    }
+    doc.group()
+}
+
+/// Returns a value which implements `std::fmt::Display` by rendering the `Debug` output of
+/// `value` at `width`.
+pub fn pretty_debug<T>(value: &T, width: usize) -> PrettyDebug<'_, T>
+where
+    T: ?Sized + fmt::Debug,
+{
+    PrettyDebug { value, width }
+}
+
+/// Displays the `Debug` output of a value laid out at a width. See `pretty_debug`.
+pub struct PrettyDebug<'a, T: ?Sized + 'a> {
+    value: &'a T,
+    width: usize,
+}
+
+impl<'a, T> fmt::Display for PrettyDebug<'a, T>
+where
+    T: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let doc: Doc<_, ()> = to_doc(&BoxAllocator, self.value).into();
+        doc.render_fmt(self.width, f)
+    }
+}
+
+/// A comma separated element: the parts it consists of and whether they were separated by
+/// whitespace.
+type Item = Vec<(bool, Part)>;
+
+enum Part {
+    Atom(String),
+    Delimited {
+        open: char,
+        items: Vec<Item>,
+        close: Option<char>,
+        padded: bool,
+    },
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    chars: Peekable<CharIndices<'s>>,
+}
+
+impl<'s> Parser<'s> {
+    /// Parses comma separated items until `close` or the end of the input, returning the items
+    /// and the closing delimiter if it was found.
+    fn items(&mut self, close: Option<char>) -> (Vec<Item>, Option<char>) {
+        let mut items = Vec::new();
+        let mut item = Item::new();
+        let mut spaced = false;
+        while let Some((start, c)) = self.chars.next() {
+            match c {
+                c if c.is_whitespace() => spaced = true,
+                ',' => {
+                    items.push(item);
+                    item = Item::new();
+                    spaced = false;
+                }
+                '(' | '[' | '{' => {
+                    let padded = self.chars.peek().map_or(false, |&(_, c)| c == ' ');
+                    let closing = match c {
+                        '(' => ')',
+                        '[' => ']',
+                        _ => '}',
+                    };
+                    let (items, close) = self.items(Some(closing));
+                    let part = Part::Delimited {
+                        open: c,
+                        items,
+                        close,
+                        padded,
+                    };
+                    item.push((spaced, part));
+                    spaced = false;
+                }
+                ')' | ']' | '}' if Some(c) == close => {
+                    items.push(item);
+                    return (finish(items), close);
+                }
+                '"' => {
+                    let end = self.string_end();
+                    item.push((spaced, Part::Atom(self.source[start..end].to_string())));
+                    spaced = false;
+                }
+                _ => {
+                    let end = if c == '\'' {
+                        self.char_end().unwrap_or_else(|| self.word_end())
+                    } else {
+                        self.word_end()
+                    };
+                    let atom = &self.source[start..end];
+                    // Merge with a directly preceding atom such as `"key"` followed by `:`
+                    match item.last_mut() {
+                        Some(&mut (_, Part::Atom(ref mut previous))) if !spaced => {
+                            previous.push_str(atom)
+                        }
+                        _ => item.push((spaced, Part::Atom(atom.to_string()))),
+                    }
+                    spaced = false;
+                }
+            }
+        }
+        items.push(item);
+        (finish(items), None)
+    }
+
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |&(i, _)| i)
+    }
+
+    fn word_end(&mut self) -> usize {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || "()[]{},\"".contains(c) {
+                break;
+            }
+            self.chars.next();
+        }
+        self.offset()
+    }
+
+    fn string_end(&mut self) -> usize {
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '\\' => {
+                    self.chars.next();
+                }
+                '"' => break,
+                _ => (),
+            }
+        }
+        self.offset()
+    }
+
+    /// Consumes the rest of a character literal such as `'a'` or `'\n'`, if there is one.
+    fn char_end(&mut self) -> Option<usize> {
+        let rest = &self.source[self.offset()..];
+        let len = if rest.starts_with('\\') {
+            rest.find('\'')? + 1
+        } else {
+            let c = rest.chars().next()?;
+            if !rest[c.len_utf8()..].starts_with('\'') {
+                return None;
+            }
+            c.len_utf8() + 1
+        };
+        let end = self.offset() + len;
+        while self.offset() < end {
+            self.chars.next();
+        }
+        Some(end)
+    }
+}
+
+/// Removes the empty items left by trailing commas.
+fn finish(mut items: Vec<Item>) -> Vec<Item> {
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+fn layout_item<'a, D, A>(allocator: &'a D, item: Item) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = allocator.nil();
+    for (i, (spaced, part)) in item.into_iter().enumerate() {
+        if spaced && i != 0 {
+            doc = doc.append(allocator.text(" "));
+        }
+        doc = doc.append(layout_part(allocator, part));
+    }
+    doc
+}
+
+fn layout_part<'a, D, A>(allocator: &'a D, part: Part) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match part {
+        Part::Atom(atom) => allocator.text(atom),
+        Part::Delimited {
+            open,
+            items,
+            close,
+            padded,
+        } => {
+            let docs = items
+                .into_iter()
+                .map(|item| layout_item(allocator, item))
+                .collect::<Vec<_>>();
+            let open = delimiter(open);
+            let close = close.map_or("", delimiter);
+            if docs.is_empty() {
+                allocator.text(open).append(allocator.text(close))
+            } else {
+                value::block(allocator, open, docs, close, value::INDENT, padded, true)
+            }
+        }
+    }
+}
+
+fn delimiter(c: char) -> &'static str {
+    match c {
+        '(' => "(",
+        ')' => ")",
+        '[' => "[",
+        ']' => "]",
+        '{' => "{",
+        _ => "}",
+    }
+}
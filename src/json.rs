@@ -0,0 +1,235 @@
+//! Pretty printing of JSON values.
+//!
+//! Arrays and objects are laid out on a single line when they fit and otherwise with one element
+//! per line. Keys, strings, numbers and literals can be annotated, for instance to color them with
+//! `render_colored`.
+//!
+//! ```
+//! use pretty::json::{Printer, Value};
+//! use pretty::BoxAllocator;
+//!
+//! let value = Value::Object(vec![
+//!     ("name".into(), Value::from("pretty")),
+//!     ("keywords".into(), Value::Array(vec!["console".into(), "functional".into()])),
+//!     ("stable".into(), Value::Bool(false)),
+//! ]);
+//! let printer = Printer::<()>::new().indent(4);
+//! let doc = printer.to_doc(&BoxAllocator, &value).1;
+//!
+//! assert_eq!(
+//!     doc.pretty(50).to_string(),
+//!     r#"{
+//!     "name": "pretty",
+//!     "keywords": ["console", "functional"],
+//!     "stable": false
+//! }"#
+//! );
+//! ```
+//!
+//! With the `serde_json` feature values can be converted from `serde_json::Value`.
+
+use std::fmt::{self, Write};
+
+#[cfg(feature = "serde_json")]
+use serde_json;
+
+use value;
+use {DocAllocator, DocBuilder};
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, stored as the text it is printed as.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// An object, with its entries in the order they are printed in.
+    Object(Vec<(String, Value)>),
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Number(n.to_string())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Number(n.to_string())
+    }
+}
+
+/// Non finite numbers are converted to `null` as JSON can not represent them.
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        if n.is_finite() {
+            Value::Number(n.to_string())
+        } else {
+            Value::Null
+        }
+    }
+}
+
+impl<'s> From<&'s str> for Value {
+    fn from(s: &'s str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Value {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(n.to_string()),
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(values) => {
+                Value::Array(values.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The kinds of tokens which can be annotated.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Kind {
+    /// The keys of objects.
+    Key,
+    String,
+    Number,
+    /// `true`, `false` and `null`.
+    Literal,
+}
+
+/// Converts JSON values into documents.
+pub struct Printer<A = ()> {
+    indent: usize,
+    annotate: Option<Box<dyn Fn(Kind) -> A>>,
+}
+
+impl<A> Default for Printer<A> {
+    fn default() -> Printer<A> {
+        Printer::new()
+    }
+}
+
+impl<A> Printer<A> {
+    /// Creates a printer which indents by 2 spaces and does not annotate anything.
+    pub fn new() -> Printer<A> {
+        Printer {
+            indent: 2,
+            annotate: None,
+        }
+    }
+
+    /// Sets the number of spaces the elements of broken arrays and objects are indented by.
+    pub fn indent(mut self, indent: usize) -> Printer<A> {
+        self.indent = indent;
+        self
+    }
+
+    /// Annotates each token with the annotation returned by `annotate` for its kind.
+    pub fn annotate<F>(mut self, annotate: F) -> Printer<A>
+    where
+        F: Fn(Kind) -> A + 'static,
+    {
+        self.annotate = Some(Box::new(annotate));
+        self
+    }
+
+    pub fn to_doc<'a, D>(&self, allocator: &'a D, value: &'a Value) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match *value {
+            Value::Null => self.token(Kind::Literal, allocator.text("null")),
+            Value::Bool(b) => {
+                let text = if b { "true" } else { "false" };
+                self.token(Kind::Literal, allocator.text(text))
+            }
+            Value::Number(ref n) => self.token(Kind::Number, allocator.text(&n[..])),
+            Value::String(ref s) => self.token(Kind::String, string(allocator, s)),
+            Value::Array(ref values) => value::block(
+                allocator,
+                "[",
+                values.iter().map(|value| self.to_doc(allocator, value)),
+                "]",
+                self.indent,
+                false,
+                false,
+            ),
+            Value::Object(ref entries) => value::block(
+                allocator,
+                "{",
+                entries.iter().map(|(key, value)| {
+                    self.token(Kind::Key, string(allocator, key))
+                        .append(allocator.text(": "))
+                        .append(self.to_doc(allocator, value))
+                }),
+                "}",
+                self.indent,
+                false,
+                false,
+            ),
+        }
+    }
+
+    fn token<'a, D>(&self, kind: Kind, doc: DocBuilder<'a, D, A>) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match self.annotate {
+            Some(ref annotate) => doc.annotate(annotate(kind)),
+            None => doc,
+        }
+    }
+}
+
+/// Returns `s` as a quoted and escaped JSON string.
+fn string<'a, D, A>(allocator: &'a D, s: &str) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escape(s, &mut escaped).expect("writing to a String can not fail");
+    allocator.text(escaped)
+}
+
+fn escape<W: Write>(s: &str, out: &mut W) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
@@ -140,11 +140,26 @@
 //! [DocBuilder](struct.DocBuilder.html) instances.  See
 //! [examples/trees.rs](https://github.com/freebroccolo/pretty.rs/blob/master/examples/trees.rs#L39)
 //! for this approach.
-
+//!
+//! Values can also be converted into documents through the [Pretty](value/trait.Pretty.html) trait,
+//! which is implemented for many types of the standard library.
+
+#[cfg(feature = "syn")]
+extern crate proc_macro2;
+#[cfg(feature = "syn")]
+extern crate quote;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+#[cfg(feature = "syn")]
+extern crate syn;
 #[cfg(feature = "termcolor")]
 pub extern crate termcolor;
 extern crate typed_arena;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fmt;
 use std::io;
@@ -152,20 +167,64 @@ use std::ops::Deref;
 #[cfg(feature = "termcolor")]
 use termcolor::{ColorSpec, WriteColor};
 
-mod render;
+#[macro_use]
+mod macros;
 
+pub mod boxes;
+pub mod debug;
+pub mod json;
+pub mod outline;
+mod render;
+#[cfg(feature = "syn")]
+pub mod rust;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod sexp;
+pub mod table;
+pub mod template;
+pub mod tree;
+pub mod value;
+pub mod xml;
+
+#[cfg(feature = "serde")]
+pub use self::render::JsonWrite;
 #[cfg(feature = "termcolor")]
 pub use self::render::TermColored;
-pub use self::render::{FmtWrite, IoWrite, Render, RenderAnnotated};
+pub use self::render::{
+    FmtWrite, Folding, IoWrite, Latex, Line, LineBuffer, MarkdownCode, Render, RenderAnnotated,
+    SpanCollector, Spans, Truncate,
+};
 
 /// The concrete document type. This type is not meant to be used directly. Instead use the static
 /// functions on `Doc` or the methods on an `DocAllocator`.
 ///
 /// The `T` parameter is used to abstract over pointers to `Doc`. See `RefDoc` and `BoxDoc` for how
 /// it is used
+///
+/// With the `serde` feature enabled documents can be serialized, and documents using an owned
+/// pointer such as `BoxDoc` can be deserialized. Deserialized text is always owned.
+///
+/// A sequence of appended documents is serialized as a single `Append` holding all of them, so
+/// long sequences do not nest deeply. It is deserialized into the left nested sequence `append`
+/// creates.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Serialize + Deref<Target = Doc<'a, T, A>>, A: Serialize",
+        deserialize = "T: Deserialize<'de> + From<Doc<'a, T, A>>, A: Deserialize<'de>"
+    ))
+)]
 pub enum Doc<'a, T, A = ()> {
     Nil,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "serialize_append",
+            deserialize_with = "deserialize_append"
+        )
+    )]
     Append(T, T),
     Group(T),
     Break(T),
@@ -175,6 +234,65 @@ pub enum Doc<'a, T, A = ()> {
     Text(Cow<'a, str>),
     Annotated(A, T),
     IfBreak(T, T),
+    Align(T),
+    Prefix(Cow<'a, str>, T),
+    TabStop,
+    AlignRight(T),
+    Center(T),
+    Fold(T, T),
+}
+
+#[cfg(feature = "serde")]
+fn serialize_append<'a, S, T, A>(left: &T, right: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    T: Serialize + Deref<Target = Doc<'a, T, A>>,
+    A: Serialize,
+{
+    use serde::ser::SerializeSeq;
+
+    let mut docs = Vec::new();
+    let mut stack = vec![&**right, &**left];
+    while let Some(doc) = stack.pop() {
+        match *doc {
+            Doc::Append(ref left, ref right) => {
+                stack.push(right);
+                stack.push(left);
+            }
+            _ => docs.push(doc),
+        }
+    }
+
+    let mut seq = serializer.serialize_seq(Some(docs.len()))?;
+    for doc in docs {
+        seq.serialize_element(doc)?;
+    }
+    seq.end()
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_append<'de, 'a, D, T, A>(deserializer: D) -> Result<(T, T), D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + From<Doc<'a, T, A>>,
+{
+    use serde::de::Error;
+
+    let docs = Vec::<T>::deserialize(deserializer)?;
+    if docs.len() < 2 {
+        return Err(D::Error::invalid_length(
+            docs.len(),
+            &"at least two appended documents",
+        ));
+    }
+    let mut docs = docs.into_iter();
+    let mut left = docs.next().expect("there are at least two documents");
+    let mut right = docs.next().expect("there are at least two documents");
+    for doc in docs {
+        left = T::from(Doc::Append(left, right));
+        right = doc;
+    }
+    Ok((left, right))
 }
 
 impl<'a, T, A> Doc<'a, T, A> {
@@ -186,7 +304,8 @@ impl<'a, T, A> Doc<'a, T, A> {
 
     /// The text `t.to_string()`.
     ///
-    /// The given text must not contain line breaks.
+    /// Line breaks in the text are written as is, without indenting the line after them. A group
+    /// containing the text is only laid out on one line if every line of the text fits.
     #[inline]
     pub fn as_string<U: ToString>(data: U) -> Doc<'a, T, A> {
         Doc::text(data.to_string())
@@ -198,7 +317,8 @@ impl<'a, T, A> Doc<'a, T, A> {
         Doc::Newline
     }
 
-    /// The given text, which must not contain line breaks.
+    /// The given text. Line breaks in it are written as is, without indenting the line after them.
+    /// A group containing the text is only laid out on one line if every line of the text fits.
     #[inline]
     pub fn text<U: Into<Cow<'a, str>>>(data: U) -> Doc<'a, T, A> {
         Doc::Text(data.into())
@@ -209,6 +329,12 @@ impl<'a, T, A> Doc<'a, T, A> {
     pub fn space() -> Doc<'a, T, A> {
         Doc::Space
     }
+
+    /// A tab stop, see [`DocAllocator::tab_stop`](trait.DocAllocator.html#method.tab_stop).
+    #[inline]
+    pub fn tab_stop() -> Doc<'a, T, A> {
+        Doc::TabStop
+    }
 }
 
 impl<'a, A> Doc<'a, BoxDoc<'a, A>, A> {
@@ -258,6 +384,13 @@ impl<'a, A> Doc<'a, BoxDoc<'a, A>, A> {
         result
     }
 
+    /// The words of `text` wrapped at the width the document is rendered with, see
+    /// [`DocAllocator::reflow`](trait.DocAllocator.html#method.reflow).
+    #[inline]
+    pub fn reflow(text: &'a str) -> Doc<'a, BoxDoc<'a, A>, A> {
+        BOX_ALLOCATOR.reflow(text).into()
+    }
+
     /// Mark this document as a group.
     ///
     /// Groups are layed out on a single line if possible.  Within a group, all basic documents with
@@ -275,6 +408,47 @@ impl<'a, A> Doc<'a, BoxDoc<'a, A>, A> {
         DocBuilder(&BOX_ALLOCATOR, self).nest(offset).into()
     }
 
+    /// Lay out this document with its indentation set to the column it starts at.
+    #[inline]
+    pub fn align(self) -> Doc<'a, BoxDoc<'a, A>, A> {
+        DocBuilder(&BOX_ALLOCATOR, self).align().into()
+    }
+
+    /// Start every line of this document with `prefix`, after the current indentation.
+    #[inline]
+    pub fn prefix<S: Into<Cow<'a, str>>>(self, prefix: S) -> Doc<'a, BoxDoc<'a, A>, A> {
+        DocBuilder(&BOX_ALLOCATOR, self).prefix(prefix).into()
+    }
+
+    /// Mark this document as foldable, with `placeholder` shown instead when it is folded.
+    #[inline]
+    pub fn foldable<D>(self, placeholder: D) -> Doc<'a, BoxDoc<'a, A>, A>
+    where
+        D: Into<Doc<'a, BoxDoc<'a, A>, A>>,
+    {
+        DocBuilder(&BOX_ALLOCATOR, self)
+            .foldable(placeholder)
+            .into()
+    }
+
+    /// Replace this document with `…` if it is wider than `max_width` on a single line.
+    #[inline]
+    pub fn elide(self, max_width: usize) -> Doc<'a, BoxDoc<'a, A>, A> {
+        DocBuilder(&BOX_ALLOCATOR, self).elide(max_width).into()
+    }
+
+    /// Pad this document so that it ends at the width the document is rendered with.
+    #[inline]
+    pub fn align_right(self) -> Doc<'a, BoxDoc<'a, A>, A> {
+        DocBuilder(&BOX_ALLOCATOR, self).align_right().into()
+    }
+
+    /// Pad this document so that it is centered between its column and the rendered width.
+    #[inline]
+    pub fn center(self) -> Doc<'a, BoxDoc<'a, A>, A> {
+        DocBuilder(&BOX_ALLOCATOR, self).center().into()
+    }
+
     #[inline]
     pub fn annotate(self, ann: A) -> Doc<'a, BoxDoc<'a, A>, A> {
         DocBuilder(&BOX_ALLOCATOR, self).annotate(ann).into()
@@ -306,6 +480,15 @@ where
 {
     doc: &'a Doc<'a, T, A>,
     width: usize,
+    folding: Folding,
+}
+
+impl<'a, T, A> Pretty<'a, T, A> {
+    /// Replaces the foldable documents chosen by `folding` with their placeholders.
+    pub fn folded(mut self, folding: Folding) -> Pretty<'a, T, A> {
+        self.folding = folding;
+        self
+    }
 }
 
 impl<'a, T, A> fmt::Display for Pretty<'a, T, A>
@@ -313,7 +496,8 @@ where
     T: Deref<Target = Doc<'a, T, A>>,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.doc.render_fmt(self.width, f)
+        self.doc
+            .render_folded(self.width, self.folding, &mut FmtWrite::new(f))
     }
 }
 
@@ -345,7 +529,34 @@ impl<'a, T, A> Doc<'a, T, A> {
         T: Deref<Target = Doc<'b, T, A>>,
         W: ?Sized + render::RenderAnnotated<A>,
     {
-        render::best(self, width, out)
+        render::best(self, width, Folding::new(), out)
+    }
+
+    /// Writes a rendered document to a `RenderAnnotated<A>` object, with the foldable documents
+    /// chosen by `folding` replaced by their placeholders.
+    #[inline]
+    pub fn render_folded<'b, W>(
+        &'b self,
+        width: usize,
+        folding: Folding,
+        out: &mut W,
+    ) -> Result<(), W::Error>
+    where
+        T: Deref<Target = Doc<'b, T, A>>,
+        W: ?Sized + render::RenderAnnotated<A>,
+    {
+        render::best(self, width, folding, out)
+    }
+
+    /// Returns a value which displays the structure of the document as an outline, see the
+    /// [`outline`](outline/index.html) module.
+    #[inline]
+    pub fn outline<'b>(&'b self) -> outline::Outline<'b, T, A>
+    where
+        T: Deref<Target = Doc<'b, T, A>>,
+        A: fmt::Debug,
+    {
+        outline::Outline::new(self)
     }
 
     /// Returns a value which implements `std::fmt::Display`
@@ -362,7 +573,11 @@ impl<'a, T, A> Doc<'a, T, A> {
     where
         T: Deref<Target = Doc<'b, T, A>>,
     {
-        Pretty { doc: self, width }
+        Pretty {
+            doc: self,
+            width,
+            folding: Folding::new(),
+        }
     }
 }
 
@@ -374,11 +589,12 @@ impl<'a, T> Doc<'a, T, ColorSpec> {
         T: Deref<Target = Doc<'b, T, ColorSpec>>,
         W: WriteColor,
     {
-        render::best(self, width, &mut TermColored::new(out))
+        render::best(self, width, Folding::new(), &mut TermColored::new(out))
     }
 }
 
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct BoxDoc<'a, A>(Box<Doc<'a, BoxDoc<'a, A>, A>>);
 
 impl<'a, A> fmt::Debug for BoxDoc<'a, A>
@@ -396,6 +612,12 @@ impl<'a, A> BoxDoc<'a, A> {
     }
 }
 
+impl<'a, A> From<Doc<'a, BoxDoc<'a, A>, A>> for BoxDoc<'a, A> {
+    fn from(doc: Doc<'a, BoxDoc<'a, A>, A>) -> BoxDoc<'a, A> {
+        BoxDoc::new(doc)
+    }
+}
+
 impl<'a, A> Deref for BoxDoc<'a, A> {
     type Target = Doc<'a, BoxDoc<'a, A>, A>;
 
@@ -422,12 +644,12 @@ where
     }
 }
 
-impl<'a, D, A> Into<Doc<'a, D::Doc, A>> for DocBuilder<'a, D, A>
+impl<'a, D, A> From<DocBuilder<'a, D, A>> for Doc<'a, D::Doc, A>
 where
     D: ?Sized + DocAllocator<'a, A>,
 {
-    fn into(self) -> Doc<'a, D::Doc, A> {
-        self.1
+    fn from(doc: DocBuilder<'a, D, A>) -> Doc<'a, D::Doc, A> {
+        doc.1
     }
 }
 
@@ -435,7 +657,7 @@ where
 pub trait DocAllocator<'a, A = ()> {
     type Doc: Deref<Target = Doc<'a, Self::Doc, A>>;
 
-    fn alloc(&'a self, Doc<'a, Self::Doc, A>) -> Self::Doc;
+    fn alloc(&'a self, doc: Doc<'a, Self::Doc, A>) -> Self::Doc;
 
     /// Allocate an empty document.
     #[inline]
@@ -455,9 +677,22 @@ pub trait DocAllocator<'a, A = ()> {
         DocBuilder(self, Doc::Space)
     }
 
+    /// Allocate a tab stop, which pads the text after it to align it with the tab stops on the
+    /// lines around it.
+    ///
+    /// In a block of consecutive lines holding tab stops, the first stops of the lines are padded
+    /// to the same column, then the second stops and so on. Tab stops in a group laid out on a
+    /// single line are ignored. The padding is added after the layout is chosen, so it is not
+    /// taken into account when checking whether a group fits.
+    #[inline]
+    fn tab_stop(&'a self) -> DocBuilder<'a, Self, A> {
+        DocBuilder(self, Doc::TabStop)
+    }
+
     /// Allocate a document containing the text `t.to_string()`.
     ///
-    /// The given text must not contain line breaks.
+    /// Line breaks in the text are written as is, without indenting the line after them. A group
+    /// containing the text is only laid out on one line if every line of the text fits.
     #[inline]
     fn as_string<U: ToString>(&'a self, data: U) -> DocBuilder<'a, Self, A> {
         self.text(data.to_string())
@@ -465,7 +700,8 @@ pub trait DocAllocator<'a, A = ()> {
 
     /// Allocate a document containing the given text.
     ///
-    /// The given text must not contain line breaks.
+    /// Line breaks in the text are written as is, without indenting the line after them. A group
+    /// containing the text is only laid out on one line if every line of the text fits.
     #[inline]
     fn text<U: Into<Cow<'a, str>>>(&'a self, data: U) -> DocBuilder<'a, Self, A> {
         DocBuilder(self, Doc::Text(data.into()))
@@ -506,9 +742,46 @@ pub trait DocAllocator<'a, A = ()> {
 
         result
     }
+
+    /// Allocate a document with the words of `text` wrapped at the width it is rendered with.
+    ///
+    /// Each run of whitespace inside a paragraph is a space which is broken when the word after it
+    /// does not fit on the line. Paragraphs are separated by blank lines in `text` and are laid
+    /// out with a blank line between them. Broken lines start at the current indentation.
+    fn reflow(&'a self, text: &'a str) -> DocBuilder<'a, Self, A> {
+        let mut paragraphs = Vec::new();
+        let mut words = Vec::new();
+        for line in text.lines() {
+            if line.trim().is_empty() {
+                if !words.is_empty() {
+                    paragraphs.push(std::mem::take(&mut words));
+                }
+            } else {
+                words.extend(line.split_whitespace());
+            }
+        }
+        if !words.is_empty() {
+            paragraphs.push(words);
+        }
+
+        let mut doc = self.nil();
+        for (i, words) in paragraphs.into_iter().enumerate() {
+            if i != 0 {
+                doc = doc.append(self.text("\n")).append(self.newline());
+            }
+            for (j, word) in words.into_iter().enumerate() {
+                if j != 0 {
+                    // Grouping each space on its own measures only the word after it
+                    doc = doc.append(self.space().group());
+                }
+                doc = doc.append(self.text(word));
+            }
+        }
+        doc
+    }
 }
 
-impl<'a, 's, D, A> DocBuilder<'a, D, A>
+impl<'a, D, A> DocBuilder<'a, D, A>
 where
     D: ?Sized + DocAllocator<'a, A>,
 {
@@ -560,6 +833,77 @@ where
         DocBuilder(allocator, Doc::Nest(offset, allocator.alloc(this)))
     }
 
+    /// Lay out this document with its indentation set to the column it starts at.
+    ///
+    /// Lines broken inside the document start at the column of its first character, which can
+    /// be used to align arguments under each other.
+    #[inline]
+    pub fn align(self) -> DocBuilder<'a, D, A> {
+        let DocBuilder(allocator, this) = self;
+        DocBuilder(allocator, Doc::Align(allocator.alloc(this)))
+    }
+
+    /// Start every line of this document with `prefix`, after the current indentation.
+    ///
+    /// The prefix is written before the first line and after each line break inside the
+    /// document, which can be used to lay out comments or quotes. Nested prefixes are written
+    /// one after another, and indentation added inside the document comes after the prefix.
+    #[inline]
+    pub fn prefix<S: Into<Cow<'a, str>>>(self, prefix: S) -> DocBuilder<'a, D, A> {
+        let DocBuilder(allocator, this) = self;
+        DocBuilder(allocator, Doc::Prefix(prefix.into(), allocator.alloc(this)))
+    }
+
+    /// Mark this document as foldable, with `placeholder` shown instead when it is folded.
+    ///
+    /// Nothing is folded unless the document is rendered with a [`Folding`](struct.Folding.html)
+    /// which chooses the foldable documents to fold, for instance those nested deeper than a
+    /// given depth. This lets the same document be shown at different levels of detail.
+    #[inline]
+    pub fn foldable<E>(self, placeholder: E) -> DocBuilder<'a, D, A>
+    where
+        E: Into<Doc<'a, D::Doc, A>>,
+    {
+        let DocBuilder(allocator, this) = self;
+        let doc = Doc::Fold(allocator.alloc(this), allocator.alloc(placeholder.into()));
+        DocBuilder(allocator, doc)
+    }
+
+    /// Replace this document with `…` if it is wider than `max_width` when laid out on a single
+    /// line, or if it can not be laid out on a single line at all.
+    ///
+    /// This keeps large values, such as long collections in log messages, from taking up more
+    /// than their share of the output.
+    #[inline]
+    pub fn elide(self, max_width: usize) -> DocBuilder<'a, D, A> {
+        match render::flat_width(&self.1) {
+            Some(width) if width <= max_width => self,
+            _ => self.0.text("…"),
+        }
+    }
+
+    /// Pad this document so that it ends at the width the document is rendered with.
+    ///
+    /// The document is laid out on a single line, after enough spaces to move it to the end of the
+    /// line. If it does not fit it is written without padding, and if it can not be laid out on a
+    /// single line it is neither padded nor forced to be flat.
+    #[inline]
+    pub fn align_right(self) -> DocBuilder<'a, D, A> {
+        let DocBuilder(allocator, this) = self;
+        DocBuilder(allocator, Doc::AlignRight(allocator.alloc(this)))
+    }
+
+    /// Pad this document so that it is centered between the column it starts at and the width
+    /// the document is rendered with.
+    ///
+    /// The document is laid out on a single line like with
+    /// [`align_right`](#method.align_right), and only the spaces before it are written.
+    #[inline]
+    pub fn center(self) -> DocBuilder<'a, D, A> {
+        let DocBuilder(allocator, this) = self;
+        DocBuilder(allocator, Doc::Center(allocator.alloc(this)))
+    }
+
     #[inline]
     pub fn annotate(self, ann: A) -> DocBuilder<'a, D, A> {
         let DocBuilder(allocator, this) = self;
@@ -581,6 +925,7 @@ where
 
 /// Newtype wrapper for `&Doc`
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(transparent))]
 pub struct RefDoc<'a, A: 'a>(&'a Doc<'a, RefDoc<'a, A>, A>);
 
 impl<'a, A> fmt::Debug for RefDoc<'a, A>
@@ -596,7 +941,7 @@ impl<'a, A> Deref for RefDoc<'a, A> {
     type Target = Doc<'a, RefDoc<'a, A>, A>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.0
     }
 }
 
@@ -647,6 +992,8 @@ impl<'a, A> DocAllocator<'a, A> for BoxAllocator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
+    use value::Pretty;
 
     macro_rules! test {
         ($size:expr, $actual:expr, $expected:expr) => {
@@ -703,6 +1050,38 @@ mod tests {
         test!(9, doc, "test test\ntest");
     }
 
+    #[test]
+    fn text_with_line_breaks() {
+        // The column after the text is the width of its last line
+        let doc = Doc::<BoxDoc<()>>::text("0123456789\nab")
+            .append(Doc::group(Doc::space().append(Doc::text("cd"))));
+        test!(6, doc, "0123456789\nab cd");
+
+        // Every line has to fit for a group to be flat
+        let doc = Doc::<BoxDoc<()>>::group(
+            Doc::text("x")
+                .append(Doc::space())
+                .append(Doc::text("ab\ncdefgh")),
+        );
+        test!(5, doc, "x\nab\ncdefgh");
+        test!(6, doc, "x ab\ncdefgh");
+
+        // Text after a group only has to fit up to its first line break
+        let doc =
+            Doc::<BoxDoc<()>>::group(Doc::text("a").append(Doc::space()).append(Doc::text("b")))
+                .append(Doc::text("cd\nefghijkl"));
+        test!(5, doc, "a bcd\nefghijkl");
+        test!(4, doc, "a\nbcd\nefghijkl");
+
+        // The line after a line break is not indented
+        let arena = Arena::<()>::new();
+        let doc = arena
+            .text("a")
+            .append(arena.newline().append(arena.text("b\nc")).nest(2))
+            .1;
+        test!(doc, "a\n  b\nc");
+    }
+
     // Tests that the `Doc::newline()` does not cause the rest of document to think that it fits on
     // a single line but instead breaks on the `Doc::space()` to fit with 6 columns
     #[test]
@@ -748,4 +1127,1385 @@ mod tests {
 
         test!(doc, "test\ntest");
     }
+
+    #[test]
+    fn collect_nested_spans() {
+        let doc = Doc::group(
+            Doc::text("f")
+                .annotate("name")
+                .append(Doc::text("(x)").annotate("args"))
+                .annotate("call")
+                .append(Doc::space())
+                .append(Doc::text("y")),
+        );
+
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(out.text(), "f(x) y");
+        assert_eq!(
+            out.spans().collect::<Vec<_>>(),
+            [
+                (&"call", 0..4, "f(x)"),
+                (&"name", 0..1, "f"),
+                (&"args", 1..4, "(x)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_annotations_end_together() {
+        let doc = Doc::<BoxDoc<_>, _>::text("x")
+            .annotate("inner")
+            .annotate("outer")
+            .append(Doc::text(" y"));
+
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(
+            out.spans().collect::<Vec<_>>(),
+            [(&"outer", 0..1, "x"), (&"inner", 0..1, "x")]
+        );
+
+        // The default collector has no bound on the annotation type
+        struct Annotation;
+        let out = SpanCollector::<Annotation>::default();
+        assert_eq!(out.text(), "");
+    }
+
+    #[test]
+    fn line_buffer_splits_spans() {
+        let doc = Doc::text("{")
+            .append(
+                Doc::newline()
+                    .append(Doc::text("a").annotate("key"))
+                    .append(Doc::text(": 1"))
+                    .nest(2),
+            )
+            .append(Doc::newline())
+            .append(Doc::text("}"))
+            .annotate("object");
+
+        let mut out = LineBuffer::new();
+        doc.render_raw(70, &mut out).unwrap();
+        let lines = out.into_lines();
+        assert_eq!(
+            lines.iter().map(Line::text).collect::<Vec<_>>(),
+            ["{", "  a: 1", "}"]
+        );
+        assert_eq!(
+            lines[1].spans,
+            [
+                ("  ".to_string(), Some("object")),
+                ("a".to_string(), Some("key")),
+                (": 1".to_string(), Some("object")),
+            ]
+        );
+    }
+
+    #[test]
+    fn latex_escapes_and_nests_macros() {
+        let doc = Doc::text("x")
+            .annotate("var")
+            .append(Doc::text(" % 100$"))
+            .annotate("expr");
+
+        let mut s = String::new();
+        {
+            let mut out = Latex::new(FmtWrite::new(&mut s), |ann: &&str| match *ann {
+                "var" => Some("\\emph".to_string()),
+                "expr" => Some("\\texttt".to_string()),
+                _ => None,
+            });
+            doc.render_raw(70, &mut out).unwrap();
+        }
+        assert_eq!(s, "\\texttt{\\emph{x} \\% 100\\$}");
+    }
+
+    #[test]
+    fn markdown_fence_is_longer_than_content() {
+        let doc = Doc::<BoxDoc<()>>::text("```");
+
+        let mut s = String::new();
+        MarkdownCode::new(FmtWrite::new(&mut s), "")
+            .finish()
+            .unwrap();
+        assert_eq!(s, "```\n```");
+
+        s.clear();
+        {
+            let mut out = MarkdownCode::new(FmtWrite::new(&mut s), "md");
+            doc.render_raw(70, &mut out).unwrap();
+            out.finish().unwrap();
+        }
+        assert_eq!(s, "````md\n```\n````");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_write_nested_annotations() {
+        let doc = Doc::text("[")
+            .append(Doc::text("1").annotate("number"))
+            .append(Doc::newline())
+            .append(Doc::text("]"))
+            .annotate("array");
+
+        let mut out = JsonWrite::new(Vec::new());
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.finish().unwrap()).unwrap(),
+            concat!(
+                r#"[[{"text":"[","annotation":"array"},{"text":"1","annotation":"number"}],"#,
+                r#"[{"text":"]","annotation":"array"}]]"#
+            )
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_write_escapes_text() {
+        let doc = Doc::<BoxDoc<()>>::text("\"a\\b\"\t\u{1}é…");
+
+        let mut out = JsonWrite::new(Vec::new());
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.finish().unwrap()).unwrap(),
+            r#"[[{"text":"\"a\\b\"\t\u0001é…","annotation":null}]]"#
+        );
+    }
+
+    #[test]
+    fn pretty_std_values() {
+        let mut map = BTreeMap::new();
+        map.insert("numbers", vec![Some(1), None]);
+        map.insert("empty", vec![]);
+        let doc: Doc<BoxDoc<()>> = map.pretty(&BoxAllocator).into();
+
+        test!(doc, r#"{"empty": [], "numbers": [Some(1), None]}"#);
+        test!(
+            20,
+            doc,
+            r#"{
+    "empty": [],
+    "numbers": [
+        Some(1),
+        None,
+    ],
+}"#
+        );
+
+        let doc: Doc<BoxDoc<()>> = (("a",), 'b', ()).pretty(&BoxAllocator).into();
+        test!(doc, r#"(("a",), 'b', ())"#);
+    }
+
+    #[test]
+    fn docs_macro() {
+        let arena = Arena::<()>::new();
+        let args = vec!["a", "b"];
+        let doc = docs![
+            &arena,
+            "call",
+            group(
+                "(",
+                nest(2, newline, arena.intersperse(args, ",")),
+                newline,
+                ")"
+            ),
+            space,
+            String::from("end"),
+        ];
+
+        test!(doc.1, "call(\n  a,b\n)\nend");
+        test!(docs![&arena].1, "");
+    }
+
+    #[test]
+    fn format_doc_template() {
+        let arena = Arena::<()>::new();
+        let doc = format_doc!(&arena, "{{{}}}  =  {}", "x", arena.text("1").nest(2)).group();
+
+        test!(doc.1, "{x} = 1");
+    }
+
+    #[test]
+    #[should_panic(expected = "missing argument")]
+    fn format_doc_missing_argument() {
+        format_doc!(&Arena::<()>::new(), "{} {}", "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "too many arguments")]
+    fn format_doc_too_many_arguments() {
+        format_doc!(&Arena::<()>::new(), "{}", "a", "b");
+    }
+
+    #[test]
+    fn json_escaping_and_annotations() {
+        let value = json::Value::Object(vec![
+            ("a\"b".into(), json::Value::from("line\n\u{1}")),
+            ("list".into(), json::Value::Array(vec![])),
+            ("n".into(), json::Value::from(1.5)),
+            ("null".into(), json::Value::Null),
+        ]);
+        let printer = json::Printer::new().annotate(|kind| kind);
+        let mut out = SpanCollector::new();
+        printer
+            .to_doc(&BoxAllocator, &value)
+            .1
+            .render_raw(70, &mut out)
+            .unwrap();
+
+        assert_eq!(
+            out.text(),
+            r#"{"a\"b": "line\n\u0001", "list": [], "n": 1.5, "null": null}"#
+        );
+        assert_eq!(
+            out.spans()
+                .map(|(kind, _, text)| (*kind, text))
+                .collect::<Vec<_>>(),
+            [
+                (json::Kind::Key, r#""a\"b""#),
+                (json::Kind::String, r#""line\n\u0001""#),
+                (json::Kind::Key, r#""list""#),
+                (json::Kind::Key, r#""n""#),
+                (json::Kind::Number, "1.5"),
+                (json::Kind::Key, r#""null""#),
+                (json::Kind::Literal, "null"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_from_serde_json() {
+        let value: serde_json::Value =
+            serde_json::from_str(r#"{"b": [1, true], "a": {}}"#).unwrap();
+        let value = json::Value::from(value);
+        let doc = json::Printer::<()>::new().to_doc(&BoxAllocator, &value);
+
+        test!(20, doc.1, "{\n  \"a\": {},\n  \"b\": [1, true]\n}");
+    }
+
+    #[test]
+    fn reformat_debug_output() {
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        enum Token {
+            Word(String),
+            Char(char),
+        }
+
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Doc {
+            tokens: Vec<Token>,
+            lookup: BTreeMap<&'static str, (u8, Option<()>)>,
+        }
+
+        let mut lookup = BTreeMap::new();
+        lookup.insert("a, [b]", (1, None));
+        lookup.insert("}", (2, Some(())));
+        let doc = Doc {
+            tokens: vec![Token::Word("x, \"y\" {".into()), Token::Char('\'')],
+            lookup,
+        };
+
+        assert_eq!(
+            debug::pretty_debug(&doc, 200).to_string(),
+            format!("{:?}", doc)
+        );
+        assert_eq!(
+            debug::pretty_debug(&doc, 40).to_string(),
+            r#"Doc {
+    tokens: [
+        Word("x, \"y\" {"),
+        Char('\''),
+    ],
+    lookup: {
+        "a, [b]": (1, None),
+        "}": (2, Some(())),
+    },
+}"#
+        );
+    }
+
+    #[test]
+    fn reformat_debug_top_level_items() {
+        struct Items;
+
+        impl fmt::Debug for Items {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("first, [1, 2], third")
+            }
+        }
+
+        assert_eq!(
+            debug::pretty_debug(&Items, 200).to_string(),
+            "first, [1, 2], third"
+        );
+        assert_eq!(
+            debug::pretty_debug(&Items, 10).to_string(),
+            "first,\n[1, 2],\nthird"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let doc = Doc::group(
+            Doc::text("key")
+                .annotate(1)
+                .append(Doc::space())
+                .append(Doc::text("value").nest(2))
+                .append(Doc::nil().if_break(Doc::text(","))),
+        );
+
+        let json = serde_json::to_string(&doc).unwrap();
+        let back: Doc<BoxDoc<i32>, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, doc);
+        test!(back, "key value");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_long_sequence() {
+        let doc: Doc<BoxDoc<()>> = Doc::concat((0..200).map(Doc::as_string));
+
+        let json = serde_json::to_string(&doc).unwrap();
+        assert!(json.starts_with(r#"{"Append":[{"Text":"0"},{"Text":"1"},"#));
+        let back: Doc<BoxDoc<()>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, doc);
+
+        assert!(serde_json::from_str::<Doc<BoxDoc<()>>>(r#"{"Append":["Space"]}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_arena_doc() {
+        let arena = Arena::<()>::new();
+        let doc = arena.text("a").append(arena.space()).group();
+
+        let json = serde_json::to_string(&doc.1).unwrap();
+        assert_eq!(json, r#"{"Group":{"Append":[{"Text":"a"},"Space"]}}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_to_doc() {
+        #[derive(Serialize)]
+        struct Config {
+            name: &'static str,
+            limits: BTreeMap<&'static str, u32>,
+            mode: Option<Mode>,
+        }
+
+        #[derive(Serialize)]
+        enum Mode {
+            Fast,
+            Retry(u8, u8),
+        }
+
+        let mut limits = BTreeMap::new();
+        limits.insert("cpu", 4);
+        limits.insert("memory", 512);
+        let config = Config {
+            name: "server",
+            limits,
+            mode: Some(Mode::Retry(3, 10)),
+        };
+
+        let doc: Doc<BoxDoc<()>> = ::ser::to_doc(&BoxAllocator, &config).unwrap().into();
+        test!(
+            100,
+            doc,
+            r#"Config { name: "server", limits: {"cpu": 4, "memory": 512}, mode: Some(Retry(3, 10)) }"#
+        );
+        test!(
+            40,
+            doc,
+            r#"Config {
+    name: "server",
+    limits: {"cpu": 4, "memory": 512},
+    mode: Some(Retry(3, 10)),
+}"#
+        );
+
+        let doc: Doc<BoxDoc<()>> = ::ser::to_doc(&BoxAllocator, &Mode::Fast).unwrap().into();
+        test!(doc, "Fast");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_one_element_tuple() {
+        #[derive(Serialize)]
+        struct Wrapper(u8);
+
+        let doc: Doc<BoxDoc<()>> = ::ser::to_doc(&BoxAllocator, &(1,)).unwrap().into();
+        test!(doc, "(1,)");
+        let doc: Doc<BoxDoc<()>> = ::ser::to_doc(&BoxAllocator, &((1, 2),)).unwrap().into();
+        test!(doc, "((1, 2),)");
+        let doc: Doc<BoxDoc<()>> = ::ser::to_doc(&BoxAllocator, &Wrapper(1)).unwrap().into();
+        test!(doc, "Wrapper(1)");
+    }
+
+    #[test]
+    fn align_to_column() {
+        let doc = Doc::<BoxDoc<()>>::text("let x = ").append(
+            Doc::text("a")
+                .append(Doc::space())
+                .append(Doc::text("b"))
+                .nest(2)
+                .align(),
+        );
+        test!(doc, "let x = a\n          b");
+    }
+
+    #[test]
+    fn flat_space_advances_column() {
+        // Reflowed words are separated by flat spaces, which count towards the width of the line
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::group(Doc::space()).append(Doc::text("b")))
+            .append(Doc::group(Doc::space()).append(Doc::text("c")));
+        test!(4, doc, "a b\nc");
+        test!(5, doc, "a b c");
+    }
+
+    #[test]
+    fn reflow_paragraphs() {
+        let text = "The quick brown fox
+            jumps over   the lazy dog.
+
+            \t
+            Pack my box with five dozen liquor jugs.
+";
+        let doc = Doc::text("note: ").append(Doc::<BoxDoc<()>>::reflow(text).nest(6));
+        test!(
+            26,
+            doc,
+            "note: The quick brown fox
+      jumps over the lazy
+      dog.
+
+      Pack my box with
+      five dozen liquor
+      jugs."
+        );
+        let rendered = doc.pretty(26).to_string();
+        assert!(rendered.lines().all(|line| line.chars().count() <= 26));
+        test!(
+            200,
+            doc,
+            "note: The quick brown fox jumps over the lazy dog.
+
+      Pack my box with five dozen liquor jugs."
+        );
+    }
+
+    #[test]
+    fn prefix_every_line() {
+        let words = || {
+            Doc::<BoxDoc<()>>::text("lorem")
+                .append(Doc::space())
+                .append(Doc::text("ipsum"))
+                .append(Doc::space())
+                .append(Doc::text("dolor"))
+                .group()
+        };
+        let comment = Doc::text("fn f() {")
+            .append(
+                Doc::newline()
+                    .append(words().prefix("// "))
+                    .append(Doc::newline())
+                    .append(Doc::text("x"))
+                    .nest(4),
+            )
+            .append(Doc::newline())
+            .append(Doc::text("}"));
+        test!(
+            14,
+            comment,
+            "fn f() {\n    // lorem\n    // ipsum\n    // dolor\n    x\n}"
+        );
+        test!(30, comment, "fn f() {\n    // lorem ipsum dolor\n    x\n}");
+
+        let quote = Doc::text("a")
+            .append(Doc::newline())
+            .append(
+                Doc::text("b:")
+                    .append(Doc::newline().append(words()).nest(2))
+                    .prefix("> "),
+            )
+            .prefix("> ");
+        test!(
+            10,
+            quote,
+            "> a\n> > b:\n> >   lorem\n> >   ipsum\n> >   dolor"
+        );
+    }
+
+    #[test]
+    fn table_columns() {
+        use table::{Alignment, Table};
+
+        let a = &BoxAllocator;
+        let table = || {
+            Table::<_, ()>::new()
+                .separator(" | ")
+                .align(1, Alignment::Center)
+                .align(2, Alignment::Right)
+                .row(vec![a.text("name"), a.text("kind"), a.text("size")])
+                .row(vec![a.text("lib.rs"), a.text("file"), a.text("52817")])
+                .row(vec![a.text("src"), a.text("dir")])
+        };
+        let doc = a
+            .text("files:")
+            .append(a.newline().append(table().to_doc(a)).nest(2))
+            .1;
+        test!(
+            doc,
+            "files:
+  name   | kind |  size
+  lib.rs | file | 52817
+  src    | dir"
+        );
+        test!(
+            20,
+            doc,
+            "files:
+  name
+    kind
+    size
+  lib.rs
+    file
+    52817
+  src
+    dir"
+        );
+
+        // A cell which always breaks makes the rows stack
+        let doc = Table::<_, ()>::new()
+            .indent(4)
+            .row(vec![a.text("x"), a.text("1")])
+            .row(vec![
+                a.text("y"),
+                a.text("a").append(a.newline()).append(a.text("b")),
+            ])
+            .to_doc(a)
+            .1;
+        test!(doc, "x\n    1\ny\n    a\n    b");
+    }
+
+    #[test]
+    fn table_non_ascii_cells() {
+        use table::{Alignment, Table};
+
+        // Widths are counted in chars, not in bytes
+        let a = &BoxAllocator;
+        let doc = Table::<_, ()>::new()
+            .align(1, Alignment::Right)
+            .row(vec![a.text("name"), a.text("größe")])
+            .row(vec![a.text("naïve"), a.text("✓")])
+            .to_doc(a)
+            .1;
+        test!(11, doc, "name  größe\nnaïve     ✓");
+
+        let doc = Doc::<BoxDoc<()>>::text("→").append(Doc::text("✓✓").align_right());
+        test!(6, doc, "→   ✓✓");
+    }
+
+    #[test]
+    fn group_with_newline_fits_all_lines() {
+        // A plain group is only flat if the line after the newline fits as well. Only the first
+        // line used to be measured, which kept `a b` on one line at width 10
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::space())
+            .append(Doc::text("b"))
+            .append(Doc::newline())
+            .append(Doc::text("a long second line"))
+            .group();
+        test!(10, doc, "a\nb\na long second line");
+        test!(20, doc, "a b\na long second line");
+
+        // The line after the newline starts at the indentation
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::space())
+            .append(Doc::text("b"))
+            .append(Doc::newline().append(Doc::text("cdef")).nest(2))
+            .group();
+        test!(5, doc, "a\nb\n  cdef");
+        test!(6, doc, "a b\n  cdef");
+
+        // Outside of a flat group only the rest of the current line has to fit
+        let doc =
+            Doc::<BoxDoc<()>>::group(Doc::text("a").append(Doc::space()).append(Doc::text("b")))
+                .append(Doc::newline())
+                .append(Doc::text("a long second line"));
+        test!(3, doc, "a b\na long second line");
+
+        // Line breaks in text follow the same rules, except that they are not indented
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::space())
+            .append(Doc::text("b\na long second line"))
+            .group();
+        test!(10, doc, "a\nb\na long second line");
+        test!(20, doc, "a b\na long second line");
+    }
+
+    #[test]
+    fn tab_stops_align_columns() {
+        let a = &BoxAllocator;
+        let arm = |pattern: &'static str, comment: &'static str| {
+            a.text(pattern)
+                .append(a.text(","))
+                .append(a.tab_stop())
+                .append(a.text(" // "))
+                .append(a.text(comment).annotate(comment))
+        };
+        let doc = a
+            .text("match x {")
+            .append(
+                a.newline()
+                    .append(arm("0 => zero()", "none"))
+                    .append(a.newline())
+                    .append(arm("1 => one(x)", "one"))
+                    .append(a.newline())
+                    .append(arm("_ => many(x, y)", "more"))
+                    .append(a.newline())
+                    .append(a.text("_ => {}"))
+                    .append(a.newline())
+                    .append(arm("_ => (x)", "unreachable"))
+                    .nest(4),
+            )
+            .append(a.newline())
+            .append(a.text("}"))
+            .1;
+        test!(
+            doc,
+            "match x {
+    0 => zero(),     // none
+    1 => one(x),     // one
+    _ => many(x, y), // more
+    _ => {}
+    _ => (x), // unreachable
+}"
+        );
+
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(
+            out.spans().map(|(_, _, text)| text).collect::<Vec<_>>(),
+            ["none", "one", "more", "unreachable"]
+        );
+
+        // Several stops on a line are aligned one after another
+        let row = |cells: &[&'static str]| {
+            let mut doc: DocBuilder<_> = a.nil();
+            for (i, &cell) in cells.iter().enumerate() {
+                if i != 0 {
+                    doc = doc.append(a.tab_stop()).append(a.text("|"));
+                }
+                doc = doc.append(a.text(cell));
+            }
+            doc
+        };
+        let doc = row(&["a", "bbb", "c"])
+            .append(a.newline())
+            .append(row(&["dd", "e", "f"]))
+            .append(a.newline())
+            .append(row(&["g"]))
+            .1;
+        test!(doc, "a |bbb|c\ndd|e  |f\ng");
+
+        // In a flat group there is nothing to align with
+        let doc = Doc::<BoxDoc<()>>::text("x")
+            .append(Doc::tab_stop())
+            .append(Doc::space())
+            .append(Doc::text("y"))
+            .group();
+        test!(doc, "x y");
+    }
+
+    #[test]
+    fn boxes_compose() {
+        use boxes::{Block, Vertical};
+        use table::Alignment;
+
+        let numbers = Block::text("1\n2\n3\n4");
+        let code = Doc::text("fn")
+            .annotate("keyword")
+            .append(Doc::space())
+            .append(Doc::text("main()"))
+            .group();
+        let code = Block::new(&code, 5);
+        assert_eq!((code.width(), code.height()), (6, 2));
+
+        let listing = Block::beside(vec![numbers.clone(), code.clone()], "  ", Vertical::Middle);
+        assert_eq!(listing.to_string(), "1\n2  fn\n3  main()\n4");
+        let listing = Block::beside(vec![code.clone(), numbers], " ", Vertical::Bottom);
+        assert_eq!(
+            listing.to_string(),
+            "       1\n       2\nfn     3\nmain() 4"
+        );
+
+        let title = Block::text("code");
+        let framed = Block::above(
+            vec![title.clone(), code.clone(), Block::empty(0, 1), title],
+            Alignment::Right,
+        );
+        assert_eq!(framed.to_string(), "  code\nfn\nmain()\n\n  code");
+        assert_eq!(
+            Block::above(vec![Block::text("ab"), code.clone()], Alignment::Center).to_string(),
+            "  ab\nfn\nmain()"
+        );
+
+        let doc = BoxAllocator
+            .text("> ")
+            .append(code.to_doc(&BoxAllocator).nest(2))
+            .1;
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(out.text(), "> fn\n  main()");
+        assert_eq!(out.spans().collect::<Vec<_>>(), [(&"keyword", 2..4, "fn")]);
+    }
+
+    #[test]
+    fn align_right_and_center() {
+        let doc = Doc::<BoxDoc<()>>::text("Report")
+            .center()
+            .append(Doc::newline())
+            .append(Doc::text("total:"))
+            .append(Doc::text("42").append(Doc::text(" items")).align_right())
+            .append(Doc::newline())
+            .append(Doc::text("fn main() {}"))
+            .append(Doc::text("// line 3").align_right());
+        test!(
+            20,
+            doc,
+            "       Report\ntotal:      42 items\nfn main() {}// line 3"
+        );
+        test!(
+            24,
+            doc,
+            "         Report\ntotal:          42 items\nfn main() {}   // line 3"
+        );
+
+        // The padding counts when checking whether a group fits
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::space())
+            .append(Doc::text("b").align_right())
+            .append(Doc::text("c"))
+            .group();
+        test!(10, doc, "a\n         bc");
+        let doc = Doc::<BoxDoc<()>>::text("a")
+            .append(Doc::space())
+            .append(Doc::text("b").center())
+            .append(Doc::text("c"))
+            .group();
+        test!(5, doc, "a  bc");
+    }
+
+    #[test]
+    fn truncate_output() {
+        let doc = Doc::text("[")
+            .append(
+                Doc::newline()
+                    .append(Doc::intersperse(
+                        ["alpha", "beta", "gamma", "delta"]
+                            .iter()
+                            .map(|&word| Doc::text(word).annotate(word)),
+                        Doc::text(",").append(Doc::newline()),
+                    ))
+                    .nest(2),
+            )
+            .append(Doc::newline())
+            .append(Doc::text("]"));
+
+        let mut out = Truncate::new(SpanCollector::new()).max_lines(3);
+        doc.render_raw(70, &mut out).unwrap();
+        assert!(out.is_truncated());
+        let out = out.into_inner();
+        assert_eq!(out.text(), "[\n  alpha,\n  beta,…");
+        assert_eq!(
+            out.spans().map(|(_, _, text)| text).collect::<Vec<_>>(),
+            ["alpha", "beta"]
+        );
+
+        let mut out = Truncate::new(SpanCollector::<&str>::new())
+            .max_bytes(12)
+            .ellipsis(" [...]");
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(out.into_inner().text(), "[\n  alpha,\n  [...]");
+
+        let mut out = Truncate::new(SpanCollector::<&str>::new())
+            .max_lines(6)
+            .max_bytes(100);
+        doc.render_raw(70, &mut out).unwrap();
+        assert!(!out.is_truncated());
+
+        // Without any lines only the ellipsis is written
+        let mut out = Truncate::new(SpanCollector::<&str>::new()).max_lines(0);
+        doc.render_raw(70, &mut out).unwrap();
+        assert!(out.is_truncated());
+        let out = out.into_inner();
+        assert_eq!(out.text(), "…");
+        assert_eq!(out.spans().count(), 0);
+
+        // Bytes are only cut at character boundaries
+        let mut s = String::new();
+        {
+            let mut out = Truncate::new(FmtWrite::new(&mut s)).max_bytes(2);
+            Doc::<BoxDoc<()>>::text("äbc")
+                .render_raw(70, &mut out)
+                .unwrap();
+        }
+        assert_eq!(s, "ä…");
+    }
+
+    #[test]
+    fn elide_wide_documents() {
+        let list = |n: usize| {
+            Doc::<BoxDoc<()>>::text("[")
+                .append(Doc::intersperse(
+                    (0..n).map(Doc::as_string),
+                    Doc::text(",").append(Doc::space()),
+                ))
+                .append(Doc::text("]"))
+                .group()
+        };
+        let doc = Doc::text("a = ")
+            .append(list(3).elide(10))
+            .append(Doc::text(", b = "))
+            .append(list(20).elide(10))
+            .append(Doc::text(", c = "))
+            .append(Doc::text("x").append(Doc::newline()).elide(10));
+        test!(doc, "a = [0, 1, 2], b = …, c = …");
+    }
+
+    #[test]
+    fn fold_nested_values() {
+        let value = json::Value::Object(vec![
+            ("id".into(), json::Value::from(1.0)),
+            (
+                "tags".into(),
+                json::Value::Array(vec![json::Value::from("a"), json::Value::Array(vec![])]),
+            ),
+            (
+                "owner".into(),
+                json::Value::Object(vec![(
+                    "groups".into(),
+                    json::Value::Array(vec![json::Value::from("admin")]),
+                )]),
+            ),
+        ]);
+        let doc = json::Printer::<()>::new().to_doc(&BoxAllocator, &value).1;
+        assert_eq!(
+            doc.pretty(80).to_string(),
+            r#"{"id": 1, "tags": ["a", []], "owner": {"groups": ["admin"]}}"#
+        );
+        assert_eq!(
+            doc.pretty(80).folded(Folding::new().depth(1)).to_string(),
+            r#"{"id": 1, "tags": […], "owner": {…}}"#
+        );
+        assert_eq!(
+            doc.pretty(80).folded(Folding::new().depth(0)).to_string(),
+            "{…}"
+        );
+
+        // Each value is folded on its own if it is too large
+        let small = json::Value::Array(vec![json::Value::from(1.0)]);
+        let printer = json::Printer::<()>::new();
+        let line = BoxAllocator
+            .text("request ")
+            .append(printer.to_doc(&BoxAllocator, &small))
+            .append(BoxAllocator.text(" response "))
+            .append(printer.to_doc(&BoxAllocator, &value))
+            .1;
+        assert_eq!(
+            line.pretty(80).folded(Folding::new().nodes(2)).to_string(),
+            "request [1] response {…}"
+        );
+        assert_eq!(
+            line.pretty(100).folded(Folding::new().nodes(4)).to_string(),
+            r#"request [1] response {"id": 1, "tags": ["a", []], "owner": {"groups": ["admin"]}}"#
+        );
+
+        // The placeholder is what has to fit when folded
+        let nested = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let doc: Doc<BoxDoc<()>> = nested.pretty(&BoxAllocator).into();
+        test!(16, doc, "[\n    [1, 2, 3],\n    [4, 5, 6],\n]");
+        let mut s = String::new();
+        doc.render_folded(16, Folding::new().depth(1), &mut FmtWrite::new(&mut s))
+            .unwrap();
+        assert_eq!(s, "[[…], […]]");
+    }
+
+    #[test]
+    fn sexp_special_forms() {
+        use sexp::{self, Printer, SExp};
+
+        let input = r#"
+            ; Greets everyone
+            (let ((names '("ada" "grace")) (count 2))
+              (dolist (name names) (print (concat "hello " name) count)))
+        "#;
+        let exprs = sexp::parse(input).unwrap();
+        assert_eq!(exprs.len(), 1);
+
+        let printer = Printer::<()>::new();
+        let doc = printer.to_doc(&BoxAllocator, &exprs[0]).1;
+        test!(
+            200,
+            doc,
+            r#"(let ((names '("ada" "grace")) (count 2)) (dolist (name names) (print (concat "hello " name) count)))"#
+        );
+        test!(
+            40,
+            doc,
+            r#"(let ((names '("ada" "grace"))
+      (count 2))
+  (dolist (name names)
+    (print (concat "hello " name)
+           count)))"#
+        );
+
+        let printer = Printer::<()>::new().indent(4).special("print", 1);
+        let doc = printer.to_doc(&BoxAllocator, &exprs[0]).1;
+        test!(
+            40,
+            doc,
+            r#"(let ((names '("ada" "grace"))
+      (count 2))
+    (dolist (name names)
+        (print (concat "hello " name)
+            count)))"#
+        );
+
+        assert_eq!(
+            "(a \"b\\\"\")".parse(),
+            Ok(SExp::List(vec![
+                SExp::Atom("a".into()),
+                SExp::String("b\"".into()),
+            ]))
+        );
+        let err = sexp::parse("(a (b)").unwrap_err();
+        assert_eq!(err.offset, 6);
+        assert_eq!(err.to_string(), "unclosed list at offset 6");
+        assert!("(a) b".parse::<SExp>().is_err());
+    }
+
+    #[test]
+    fn sexp_string_round_trip() {
+        use sexp::{Printer, SExp};
+
+        let expr = SExp::List(vec![
+            SExp::Atom("print".into()),
+            SExp::String("quote \" backslash \\ tab \t cr \r bell \u{7} é ✓ 'x'\nend".into()),
+        ]);
+        let doc = Printer::<()>::new().to_doc(&BoxAllocator, &expr).1;
+        let printed = doc.pretty(200).to_string();
+        assert_eq!(
+            printed,
+            "(print \"quote \\\" backslash \\\\ tab \\t cr \r bell \u{7} é ✓ 'x'\\nend\")"
+        );
+        assert_eq!(printed.parse(), Ok(expr));
+    }
+
+    #[test]
+    fn xml_elements() {
+        use xml::{Element, Kind, Node, Printer};
+
+        let form: Node = Element::new("form")
+            .attr("action", "/search?q=a&b")
+            .attr("method", "get")
+            .child(Element::new("br"))
+            .child(Element::new("pre").child("  keep\n    this"))
+            .child(Element::new("label").child(" 1 < 2 "))
+            .into();
+
+        let printer = Printer::<()>::new();
+        let doc = printer.to_doc(&BoxAllocator, &form).1;
+        test!(
+            200,
+            doc,
+            r#"<form action="/search?q=a&amp;b" method="get"><br/><pre>  keep
+    this</pre><label>1 &lt; 2</label></form>"#
+        );
+        test!(
+            30,
+            doc,
+            r#"<form
+  action="/search?q=a&amp;b"
+  method="get">
+  <br/>
+  <pre>  keep
+    this</pre>
+  <label>1 &lt; 2</label>
+</form>"#
+        );
+
+        let label: Node = Element::new("label").attr("for", "q").child("Query").into();
+        let printer = Printer::new().annotate(|kind| kind);
+        let doc = printer.to_doc(&BoxAllocator, &label).1;
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(out.text(), r#"<label for="q">Query</label>"#);
+        assert_eq!(
+            out.spans()
+                .map(|(kind, _, text)| (*kind, text))
+                .collect::<Vec<_>>(),
+            [
+                (Kind::Tag, "<label"),
+                (Kind::Attribute, "for"),
+                (Kind::Value, "\"q\""),
+                (Kind::Tag, ">"),
+                (Kind::Tag, "</label>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_connectors() {
+        use tree::{Kind, Printer, Tree};
+
+        let a = &BoxAllocator;
+        let build = || {
+            Tree::new(a.text("serde v1.0"))
+                .child(
+                    Tree::new(a.text("serde_derive v1.0"))
+                        .child(Tree::new(a.text("proc-macro2 v1.0")))
+                        .child(Tree::new(a.text("quote v1.0"))),
+                )
+                .child(Tree::new(
+                    a.text("[build-dependencies]")
+                        .append(a.newline())
+                        .append(a.text("cc v1.0")),
+                ))
+        };
+
+        let doc = Printer::<()>::new().to_doc(a, build()).1;
+        test!(
+            doc,
+            "serde v1.0
+├── serde_derive v1.0
+│   ├── proc-macro2 v1.0
+│   └── quote v1.0
+└── [build-dependencies]
+    cc v1.0"
+        );
+
+        let doc = Printer::<()>::new().ascii().depth(1).to_doc(a, build()).1;
+        test!(
+            doc,
+            "serde v1.0
+|-- serde_derive v1.0 …
+`-- [build-dependencies]
+    cc v1.0"
+        );
+
+        let tree = Tree::new(a.text("a")).child(Tree::new(a.text("b")));
+        let doc = Printer::new().annotate(|kind| kind).to_doc(a, tree).1;
+        let mut out = SpanCollector::new();
+        doc.render_raw(70, &mut out).unwrap();
+        assert_eq!(
+            out.spans()
+                .map(|(kind, _, text)| (*kind, text))
+                .collect::<Vec<_>>(),
+            [
+                (Kind::Label, "a"),
+                (Kind::Connector, "└── "),
+                (Kind::Label, "b"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "syn")]
+    #[test]
+    fn format_rust_source() {
+        let source = r#"
+            use std::fmt; use std::io;
+            #[derive(Debug)] pub struct Config<T> where T: Clone { pub name: String, values: Vec<T> }
+            impl<T: Clone> Config<T> {
+                /// Creates a config
+                pub fn new(name: &str) -> Self { Config { name: name.to_string(), values: vec![] } }
+                fn check(&self, limit: usize) -> Result<(), String> {
+                    let total = self.values.iter().filter(|v| v.is_valid()).count();
+                    if total > limit { Err(format!("{} values", total)) } else { Ok(()) }
+                }
+            }
+        "#;
+        let file: ::syn::File = ::syn::parse_str(source).unwrap();
+        let doc: Doc<BoxDoc<()>> = ::rust::file(&BoxAllocator, &file).into();
+
+        test!(
+            100,
+            doc,
+            r#"use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub struct Config<T>
+where
+    T: Clone,
+{
+    pub name: String,
+    values: Vec<T>,
+}
+
+impl<T: Clone> Config<T> {
+    /// Creates a config
+    pub fn new(name: &str) -> Self {
+        Config { name: name.to_string(), values: vec![] }
+    }
+
+    fn check(&self, limit: usize) -> Result<(), String> {
+        let total = self.values.iter().filter(|v| v.is_valid()).count();
+        if total > limit { Err(format!("{} values", total)) } else { Ok(()) }
+    }
+}"#
+        );
+
+        let item: ::syn::Item = ::syn::parse_str(
+            "fn check(&self) { let total = self.values.iter().filter(|v| v.is_valid()).count(); }",
+        )
+        .unwrap();
+        let doc: Doc<BoxDoc<()>> = ::rust::item(&BoxAllocator, &item).into();
+        test!(
+            40,
+            doc,
+            r#"fn check(&self) {
+    let total = self.values
+        .iter()
+        .filter(|v| v.is_valid())
+        .count();
+}"#
+        );
+    }
+
+    #[cfg(feature = "syn")]
+    #[test]
+    fn format_rust_attributes_and_where_clauses() {
+        let source = r#"
+            //! Crate docs
+            mod m { #![allow(dead_code)] fn f() {} }
+            /** a
+             b*/
+            fn h() { #![allow(unused)] let x = 1; }
+            /// / not a comment
+            struct Tuple<T>(T) where T: Clone;
+            #[doc = "/ line comment"]
+            struct Unit<T> where T: Clone;
+            trait Tr { fn g<T>(t: T) where T: Copy; }
+            impl Tr for () { #![allow(unused)] fn g<T>(t: T) where T: Copy {} }
+        "#;
+        let print = |file: &::syn::File| {
+            let doc: Doc<BoxDoc<()>> = ::rust::file(&BoxAllocator, file).into();
+            doc.pretty(80).to_string()
+        };
+
+        let printed = print(&::syn::parse_str(source).unwrap());
+        assert_eq!(
+            printed,
+            r#"//! Crate docs
+
+mod m {
+    #![allow(dead_code)]
+
+    fn f() {}
+}
+
+/// a
+///             b
+fn h() {
+    #![allow(unused)]
+    let x = 1;
+}
+
+/// / not a comment
+struct Tuple<T>(T)
+where
+    T: Clone;
+
+#[doc = "/ line comment"]
+struct Unit<T>
+where
+    T: Clone;
+
+trait Tr {
+    fn g<T>(t: T)
+    where
+        T: Copy;
+}
+
+impl Tr for () {
+    #![allow(unused)]
+
+    fn g<T>(t: T)
+    where
+        T: Copy,
+    {}
+}"#
+        );
+        let reparsed: ::syn::File = ::syn::parse_str(&printed).unwrap();
+        assert_eq!(print(&reparsed), printed);
+    }
+
+    #[cfg(feature = "syn")]
+    #[test]
+    fn format_rust_expressions() {
+        let source = r#"
+            fn f() {
+                let p = Point { x: 1, y: 2 };
+                let q = Point { x: some_long_function_name(1), y: another_long_function_name(2) };
+                let r = Point { x: some_long_function_name(1), y: another_long_function_name(2), ..p };
+                let s = compute(first_argument_value, second_argument_value, third_argument);
+                let add = |a: i32, b: i32| a + b;
+                let c = move || { let v = 1; v + 1 };
+                match p { Point { x: 0, .. } => 1, Point { x, y } if x > y => { x } _ => 0 }
+                let v = values.iter().map(|v| v * 2).filter(|v| *v > 10).collect::<Vec<i32>>();
+                if a { if b { c(); } }
+            }
+        "#;
+        let file: ::syn::File = ::syn::parse_str(source).unwrap();
+        let doc: Doc<BoxDoc<()>> = ::rust::file(&BoxAllocator, &file).into();
+
+        test!(
+            60,
+            doc,
+            r#"fn f() {
+    let p = Point { x: 1, y: 2 };
+    let q = Point {
+        x: some_long_function_name(1),
+        y: another_long_function_name(2),
+    };
+    let r = Point {
+        x: some_long_function_name(1),
+        y: another_long_function_name(2),
+        ..p
+    };
+    let s = compute(
+        first_argument_value,
+        second_argument_value,
+        third_argument,
+    );
+    let add = |a: i32, b: i32| a + b;
+    let c = move || {
+        let v = 1;
+        v + 1
+    };
+    match p {
+        Point { x: 0, .. } => 1,
+        Point { x, y } if x > y => {
+            x
+        }
+        _ => 0,
+    }
+    let v = values
+        .iter()
+        .map(|v| v * 2)
+        .filter(|v| *v > 10)
+        .collect::<Vec<i32>>();
+    if a {
+        if b {
+            c();
+        }
+    }
+}"#
+        );
+        test!(
+            100,
+            doc,
+            r#"fn f() {
+    let p = Point { x: 1, y: 2 };
+    let q = Point { x: some_long_function_name(1), y: another_long_function_name(2) };
+    let r = Point { x: some_long_function_name(1), y: another_long_function_name(2), ..p };
+    let s = compute(first_argument_value, second_argument_value, third_argument);
+    let add = |a: i32, b: i32| a + b;
+    let c = move || {
+        let v = 1;
+        v + 1
+    };
+    match p {
+        Point { x: 0, .. } => 1,
+        Point { x, y } if x > y => {
+            x
+        }
+        _ => 0,
+    }
+    let v = values.iter().map(|v| v * 2).filter(|v| *v > 10).collect::<Vec<i32>>();
+    if a {
+        if b {
+            c();
+        }
+    }
+}"#
+        );
+    }
+
+    #[cfg(feature = "syn")]
+    #[test]
+    fn format_rust_empty_items_and_tokens() {
+        let source = r#"
+            struct Empty {}
+            enum Never {}
+            #[doc = 1 > 2]
+            #[cfg_attr(all(), path = "x")]
+            fn f() {
+                m! { a > b; c < d; x as Vec<u8>; }
+                let w: Vec<Vec<u8>> = Vec::new();
+                assert!(a <= b && c >= d, "{}", x.collect::<Vec<u8>>());
+            }
+        "#;
+        let print = |file: &::syn::File| {
+            let doc: Doc<BoxDoc<()>> = ::rust::file(&BoxAllocator, file).into();
+            doc.pretty(80).to_string()
+        };
+
+        let printed = print(&::syn::parse_str(source).unwrap());
+        assert_eq!(
+            printed,
+            r#"struct Empty {}
+
+enum Never {}
+
+#[doc = 1 > 2]
+#[cfg_attr(all(), path = "x")]
+fn f() {
+    m! { a > b; c < d; x as Vec<u8>; }
+    let w: Vec<Vec<u8>> = Vec::new();
+    assert!(a <= b && c >= d, "{}", x.collect::<Vec<u8>>());
+}"#
+        );
+        let reparsed: ::syn::File = ::syn::parse_str(&printed).unwrap();
+        assert_eq!(print(&reparsed), printed);
+    }
+
+    #[test]
+    fn outline_structure() {
+        let a = &BoxAllocator;
+        let doc: Doc<BoxDoc<()>> = a
+            .text("[")
+            .append(a.text("a"))
+            .append(a.text(",").if_break(a.text(", ")))
+            .append(a.space().append(a.text("b")).prefix("> "))
+            .append(a.text("]"))
+            .foldable(a.text("[…]"))
+            .align()
+            .into();
+        assert_eq!(
+            doc.outline().to_string(),
+            r#"Align [8]
+└── Fold [8]
+    ├── Append [8]
+    │   ├── Text "[" [1]
+    │   ├── Text "a" [1]
+    │   ├── IfBreak [1]
+    │   │   ├── break: Text ", " [2]
+    │   │   └── flat: Text "," [1]
+    │   ├── Prefix "> " [4]
+    │   │   └── Append [2]
+    │   │       ├── Space
+    │   │       └── Text "b" [1]
+    │   └── Text "]" [1]
+    └── placeholder: Text "[…]" [3]"#
+        );
+    }
 }
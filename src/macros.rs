@@ -0,0 +1,102 @@
+/// Concatenates a sequence of documents allocated with the allocator given as the first
+/// argument.
+///
+/// Each element is either an expression which can be converted into a document (such as a string
+/// or a `DocBuilder`) or one of the following markers:
+///
+/// * `space` - a `space()`, which is a line break if the enclosing group does not fit
+/// * `newline` - a `newline()`
+/// * `group(...)` - the concatenation of the elements inside the parentheses as a group
+/// * `nest(n, ...)` - the concatenation of the elements after `n` nested by `n`
+///
+/// The allocator expression is evaluated once for every element so it should be a simple
+/// expression such as `&arena`.
+///
+/// ```
+/// #[macro_use]
+/// extern crate pretty;
+///
+/// use pretty::{Arena, DocAllocator};
+///
+/// # fn main() {
+/// let arena = Arena::<()>::new();
+/// let name = arena.text("main");
+/// let doc = docs![
+///     &arena,
+///     "fn ",
+///     name,
+///     "() {",
+///     nest(4, space, "body();"),
+///     space,
+///     "}"
+/// ]
+/// .group();
+///
+/// assert_eq!(doc.1.pretty(80).to_string(), "fn main() { body(); }");
+/// assert_eq!(doc.1.pretty(10).to_string(), "fn main() {\n    body();\n}");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! docs {
+    (@concat $alloc:expr; $acc:expr;) => {
+        $acc
+    };
+    (@concat $alloc:expr; $acc:expr; space $(, $($rest:tt)*)?) => {
+        docs!(@concat $alloc; $acc.append($alloc.space()); $($($rest)*)?)
+    };
+    (@concat $alloc:expr; $acc:expr; newline $(, $($rest:tt)*)?) => {
+        docs!(@concat $alloc; $acc.append($alloc.newline()); $($($rest)*)?)
+    };
+    (@concat $alloc:expr; $acc:expr; group($($inner:tt)*) $(, $($rest:tt)*)?) => {
+        docs!(
+            @concat $alloc;
+            $acc.append(docs![$alloc, $($inner)*].group());
+            $($($rest)*)?
+        )
+    };
+    (@concat $alloc:expr; $acc:expr; nest($indent:expr, $($inner:tt)*) $(, $($rest:tt)*)?) => {
+        docs!(
+            @concat $alloc;
+            $acc.append(docs![$alloc, $($inner)*].nest($indent));
+            $($($rest)*)?
+        )
+    };
+    (@concat $alloc:expr; $acc:expr; $doc:expr $(, $($rest:tt)*)?) => {
+        docs!(@concat $alloc; $acc.append($doc); $($($rest)*)?)
+    };
+    ($alloc:expr $(,)?) => {
+        $alloc.nil()
+    };
+    ($alloc:expr, $($rest:tt)*) => {
+        docs!(@concat $alloc; $alloc.nil(); $($rest)*)
+    };
+}
+
+/// Builds a document from a template where each `{}` is replaced by the next argument and each
+/// run of whitespace becomes a `space()`. See [`Template`](template/struct.Template.html).
+///
+/// ```
+/// #[macro_use]
+/// extern crate pretty;
+///
+/// use pretty::{Arena, DocAllocator};
+///
+/// # fn main() {
+/// let arena = Arena::<()>::new();
+/// let ret = arena.text("Vec<u8>");
+/// let doc = format_doc!(&arena, "fn {}({}) -> {}", "load", "path: &Path", ret).group();
+///
+/// assert_eq!(
+///     doc.1.pretty(80).to_string(),
+///     "fn load(path: &Path) -> Vec<u8>"
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! format_doc {
+    ($alloc:expr, $template:expr $(, $arg:expr)* $(,)?) => {
+        $crate::template::Template::new($alloc, $template)
+            $(.arg($arg))*
+            .build()
+    };
+}
@@ -0,0 +1,171 @@
+//! An outline of the structure of a document, for finding out why it is laid out the way it is.
+//!
+//! Each node of the document is put on its own line with its kind, and the width it takes up when
+//! laid out on a single line, or `breaks` if it can not be. Sequences of appended documents are
+//! shown as a single `Append` node.
+//!
+//! ```
+//! use pretty::{BoxAllocator, DocAllocator};
+//!
+//! let a = &BoxAllocator;
+//! let doc = a
+//!     .text("f(")
+//!     .append(a.newline().append(a.text("x").annotate("arg")).nest(4))
+//!     .append(a.text(")"))
+//!     .group()
+//!     .1;
+//!
+//! assert_eq!(
+//!     doc.outline().to_string(),
+//!     r#"Group [breaks]
+//! └── Append [breaks]
+//!     ├── Text "f(" [2]
+//!     ├── Nest 4 [breaks]
+//!     │   └── Append [breaks]
+//!     │       ├── Newline
+//!     │       └── Annotated "arg" [1]
+//!     │           └── Text "x" [1]
+//!     └── Text ")" [1]"#
+//! );
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+
+use render::text_width;
+use tree::{Printer, Tree};
+use {BoxAllocator, Doc, DocAllocator, DocBuilder};
+
+/// Returns the outline of `doc` as a document.
+pub fn to_doc<'a, 'd, D, T, A, B>(allocator: &'a D, doc: &'d Doc<'d, T, A>) -> DocBuilder<'a, D, B>
+where
+    D: ?Sized + DocAllocator<'a, B>,
+    T: Deref<Target = Doc<'d, T, A>>,
+    A: fmt::Debug,
+{
+    let (tree, _) = node(allocator, "", doc);
+    Printer::new().to_doc(allocator, tree)
+}
+
+/// Displays the outline of a document. See `Doc::outline`.
+pub struct Outline<'d, T: 'd, A: 'd> {
+    doc: &'d Doc<'d, T, A>,
+}
+
+impl<'d, T, A> Outline<'d, T, A> {
+    pub(crate) fn new(doc: &'d Doc<'d, T, A>) -> Outline<'d, T, A> {
+        Outline { doc }
+    }
+}
+
+impl<'d, T, A> fmt::Display for Outline<'d, T, A>
+where
+    T: Deref<Target = Doc<'d, T, A>>,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let doc: Doc<_, ()> = to_doc(&BoxAllocator, self.doc).into();
+        // The labels never break, so the width does not matter
+        doc.render_fmt(usize::MAX, f)
+    }
+}
+
+/// Returns the outline of `doc`, with `role` in front of its label, and its flat width.
+fn node<'a, 'd, D, T, A, B>(
+    allocator: &'a D,
+    role: &str,
+    doc: &'d Doc<'d, T, A>,
+) -> (Tree<'a, D, B>, Option<usize>)
+where
+    D: ?Sized + DocAllocator<'a, B>,
+    T: Deref<Target = Doc<'d, T, A>>,
+    A: fmt::Debug,
+{
+    let mut children = Vec::new();
+    let mut child = |role: &str, doc: &'d Doc<'d, T, A>| {
+        let (tree, width) = node(allocator, role, doc);
+        children.push(tree);
+        width
+    };
+
+    let (kind, width) = match *doc {
+        Doc::Nil => ("Nil".to_string(), Some(0)),
+        Doc::Append(..) => {
+            let mut width = Some(0);
+            for doc in appended(doc) {
+                width = match (width, child("", doc)) {
+                    (Some(width), Some(child)) => Some(width + child),
+                    _ => None,
+                };
+            }
+            ("Append".to_string(), width)
+        }
+        Doc::Group(ref doc) => ("Group".to_string(), child("", doc)),
+        Doc::Break(ref doc) => {
+            child("", doc);
+            ("Break".to_string(), None)
+        }
+        Doc::Nest(offset, ref doc) => (format!("Nest {}", offset), child("", doc)),
+        Doc::Space => ("Space".to_string(), Some(1)),
+        Doc::Newline => ("Newline".to_string(), None),
+        Doc::Text(ref text) => {
+            let width = if text.contains('\n') {
+                None
+            } else {
+                Some(text_width(text))
+            };
+            (format!("Text {:?}", text), width)
+        }
+        Doc::Annotated(ref annotation, ref doc) => {
+            (format!("Annotated {:?}", annotation), child("", doc))
+        }
+        Doc::IfBreak(ref yes, ref no) => {
+            child("break: ", yes);
+            ("IfBreak".to_string(), child("flat: ", no))
+        }
+        Doc::Align(ref doc) => ("Align".to_string(), child("", doc)),
+        Doc::Prefix(ref prefix, ref doc) => {
+            let width = child("", doc).map(|width| text_width(prefix) + width);
+            (format!("Prefix {:?}", prefix), width)
+        }
+        Doc::TabStop => ("TabStop".to_string(), Some(0)),
+        Doc::AlignRight(ref doc) => ("AlignRight".to_string(), child("", doc)),
+        Doc::Center(ref doc) => ("Center".to_string(), child("", doc)),
+        Doc::Fold(ref doc, ref placeholder) => {
+            let width = child("", doc);
+            child("placeholder: ", placeholder);
+            ("Fold".to_string(), width)
+        }
+    };
+
+    let label = match *doc {
+        // The widths of the leaves without text are obvious
+        Doc::Nil | Doc::Space | Doc::Newline | Doc::TabStop => format!("{}{}", role, kind),
+        _ => match width {
+            Some(width) => format!("{}{} [{}]", role, kind, width),
+            None => format!("{}{} [breaks]", role, kind),
+        },
+    };
+    let mut tree = Tree::new(allocator.text(label));
+    tree.children = children;
+    (tree, width)
+}
+
+/// The documents of a sequence of appended documents, in order.
+fn appended<'d, T, A>(doc: &'d Doc<'d, T, A>) -> Vec<&'d Doc<'d, T, A>>
+where
+    T: Deref<Target = Doc<'d, T, A>>,
+{
+    let mut docs = Vec::new();
+    let mut stack = vec![doc];
+    while let Some(doc) = stack.pop() {
+        match *doc {
+            Doc::Append(ref left, ref right) => {
+                stack.push(right);
+                stack.push(left);
+            }
+            _ => docs.push(doc),
+        }
+    }
+    docs
+}
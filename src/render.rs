@@ -1,7 +1,13 @@
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+#[cfg(feature = "serde")]
+use serde_json;
 use std::cmp;
 use std::fmt;
 use std::io;
-use std::ops::Deref;
+use std::mem;
+use std::ops::{Deref, Range};
+use std::slice;
 #[cfg(feature = "termcolor")]
 use termcolor::{ColorSpec, WriteColor};
 
@@ -106,6 +112,674 @@ where
     }
 }
 
+/// Renders into a `String` while recording the range of text covered by each annotation.
+///
+/// Spans are listed in the order their annotations were entered, so an enclosing annotation is
+/// always listed before the annotations nested inside of it.
+///
+/// ```
+/// use pretty::{Doc, SpanCollector};
+///
+/// let doc = Doc::text("let").annotate("keyword")
+///     .append(Doc::space())
+///     .append(Doc::text("x").annotate("variable"));
+/// let mut out = SpanCollector::new();
+/// doc.render_raw(80, &mut out).unwrap();
+///
+/// let spans: Vec<_> = out.spans().collect();
+/// assert_eq!(spans, [(&"keyword", 0..3, "let"), (&"variable", 4..5, "x")]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpanCollector<A> {
+    buffer: String,
+    spans: Vec<(A, Range<usize>)>,
+    open: Vec<usize>,
+}
+
+impl<A> Default for SpanCollector<A> {
+    fn default() -> SpanCollector<A> {
+        SpanCollector::new()
+    }
+}
+
+impl<A> SpanCollector<A> {
+    pub fn new() -> SpanCollector<A> {
+        SpanCollector {
+            buffer: String::new(),
+            spans: Vec::new(),
+            open: Vec::new(),
+        }
+    }
+
+    /// The text rendered so far.
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Returns each annotation together with the byte range and the text it covers.
+    pub fn spans(&self) -> Spans<'_, A> {
+        Spans {
+            buffer: &self.buffer,
+            iter: self.spans.iter(),
+        }
+    }
+
+    /// Returns the rendered text and the annotated byte ranges.
+    pub fn into_parts(self) -> (String, Vec<(A, Range<usize>)>) {
+        (self.buffer, self.spans)
+    }
+}
+
+impl<A> Render for SpanCollector<A> {
+    type Error = fmt::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<usize, fmt::Error> {
+        self.buffer.push_str(s);
+        Ok(s.len())
+    }
+}
+
+impl<A> RenderAnnotated<A> for SpanCollector<A>
+where
+    A: Clone,
+{
+    fn push_annotation(&mut self, annotation: &A) -> Result<(), Self::Error> {
+        let start = self.buffer.len();
+        self.open.push(self.spans.len());
+        self.spans.push((annotation.clone(), start..start));
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        if let Some(index) = self.open.pop() {
+            self.spans[index].1.end = self.buffer.len();
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the annotated spans of a `SpanCollector`.
+pub struct Spans<'a, A: 'a> {
+    buffer: &'a str,
+    iter: slice::Iter<'a, (A, Range<usize>)>,
+}
+
+impl<'a, A> Iterator for Spans<'a, A> {
+    type Item = (&'a A, Range<usize>, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|(ann, range)| (ann, range.clone(), &self.buffer[range.clone()]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// A single rendered line, split into spans of text sharing the same annotation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Line<A> {
+    /// Each span holds the innermost annotation that was active when its text was written.
+    pub spans: Vec<(String, Option<A>)>,
+}
+
+impl<A> Line<A> {
+    /// The text of the line without any annotations.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| &span.0[..]).collect()
+    }
+}
+
+/// Serializes as a list of `{ "text": .., "annotation": .. }` segments.
+#[cfg(feature = "serde")]
+impl<A> Serialize for Line<A>
+where
+    A: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Segment<'a, A: 'a>(&'a str, Option<&'a A>);
+
+        impl<'a, A> Serialize for Segment<'a, A>
+        where
+            A: Serialize,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("text", self.0)?;
+                map.serialize_entry("annotation", &self.1)?;
+                map.end()
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.spans.len()))?;
+        for (text, annotation) in &self.spans {
+            seq.serialize_element(&Segment(text, annotation.as_ref()))?;
+        }
+        seq.end()
+    }
+}
+
+/// Renders into a list of lines made up of annotated spans, for targets such as terminal widgets
+/// which lay out styled text themselves.
+///
+/// ```
+/// use pretty::{Doc, Line, LineBuffer};
+///
+/// let doc = Doc::text("fn").annotate("keyword")
+///     .append(Doc::newline())
+///     .append(Doc::text("main"));
+/// let mut out = LineBuffer::new();
+/// doc.render_raw(80, &mut out).unwrap();
+///
+/// assert_eq!(out.into_lines(), [
+///     Line { spans: vec![("fn".to_string(), Some("keyword"))] },
+///     Line { spans: vec![("main".to_string(), None)] },
+/// ]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LineBuffer<A> {
+    lines: Vec<Line<A>>,
+    annotations: Vec<A>,
+    new_span: bool,
+}
+
+impl<A> Default for LineBuffer<A> {
+    fn default() -> LineBuffer<A> {
+        LineBuffer::new()
+    }
+}
+
+impl<A> LineBuffer<A> {
+    pub fn new() -> LineBuffer<A> {
+        LineBuffer {
+            lines: vec![Line { spans: Vec::new() }],
+            annotations: Vec::new(),
+            new_span: true,
+        }
+    }
+
+    /// The lines rendered so far.
+    pub fn lines(&self) -> &[Line<A>] {
+        &self.lines
+    }
+
+    pub fn into_lines(self) -> Vec<Line<A>> {
+        self.lines
+    }
+}
+
+impl<A> LineBuffer<A>
+where
+    A: Clone,
+{
+    fn push_text(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        let line = self
+            .lines
+            .last_mut()
+            .expect("there is always a current line");
+        match line.spans.last_mut() {
+            Some(span) if !self.new_span => span.0.push_str(s),
+            _ => line
+                .spans
+                .push((s.to_string(), self.annotations.last().cloned())),
+        }
+        self.new_span = false;
+    }
+}
+
+impl<A> Render for LineBuffer<A>
+where
+    A: Clone,
+{
+    type Error = fmt::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<usize, fmt::Error> {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.push_text(first);
+        }
+        for line in lines {
+            self.lines.push(Line { spans: Vec::new() });
+            self.new_span = true;
+            self.push_text(line);
+        }
+        Ok(s.len())
+    }
+}
+
+impl<A> RenderAnnotated<A> for LineBuffer<A>
+where
+    A: Clone,
+{
+    fn push_annotation(&mut self, annotation: &A) -> Result<(), Self::Error> {
+        self.annotations.push(annotation.clone());
+        self.new_span = true;
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        self.annotations.pop();
+        self.new_span = true;
+        Ok(())
+    }
+}
+
+/// Writes LaTeX to an upstream `Render`, escaping the characters which are special to LaTeX and
+/// wrapping annotated text in the macro chosen for its annotation.
+///
+/// Line breaks and indentation are written as is so the output is meant to be placed in an
+/// environment which preserves them, such as `alltt`.
+///
+/// ```
+/// use pretty::{Doc, FmtWrite, Latex};
+///
+/// let doc = Doc::text("fn").annotate(true)
+///     .append(Doc::space())
+///     .append(Doc::text("a_b() {}"))
+///     .group();
+/// let mut s = String::new();
+/// {
+///     let mut out = Latex::new(FmtWrite::new(&mut s), |&bold: &bool| {
+///         if bold { Some("\\textbf".to_string()) } else { None }
+///     });
+///     doc.render_raw(80, &mut out).unwrap();
+/// }
+/// assert_eq!(s, "\\textbf{fn} a\\_b() \\{\\}");
+/// ```
+pub struct Latex<W, F> {
+    upstream: W,
+    macros: F,
+    open: Vec<bool>,
+}
+
+impl<W, F> Latex<W, F> {
+    /// Creates a LaTeX writer where `macros` returns the macro (such as `\textbf`) to wrap an
+    /// annotation in, or `None` to leave the annotated text as is.
+    pub fn new(upstream: W, macros: F) -> Latex<W, F> {
+        Latex {
+            upstream,
+            macros,
+            open: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.upstream
+    }
+}
+
+impl<W, F> Render for Latex<W, F>
+where
+    W: Render,
+{
+    type Error = W::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<usize, W::Error> {
+        self.write_str_all(s).map(|_| s.len())
+    }
+
+    fn write_str_all(&mut self, s: &str) -> Result<(), W::Error> {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            let escaped = match c {
+                '\\' => "\\textbackslash{}",
+                '{' => "\\{",
+                '}' => "\\}",
+                '$' => "\\$",
+                '&' => "\\&",
+                '#' => "\\#",
+                '%' => "\\%",
+                '_' => "\\_",
+                '^' => "\\textasciicircum{}",
+                '~' => "\\textasciitilde{}",
+                _ => continue,
+            };
+            self.upstream.write_str_all(&s[start..i])?;
+            self.upstream.write_str_all(escaped)?;
+            start = i + c.len_utf8();
+        }
+        self.upstream.write_str_all(&s[start..])
+    }
+}
+
+impl<A, W, F> RenderAnnotated<A> for Latex<W, F>
+where
+    W: Render,
+    F: FnMut(&A) -> Option<String>,
+{
+    fn push_annotation(&mut self, annotation: &A) -> Result<(), Self::Error> {
+        match (self.macros)(annotation) {
+            Some(name) => {
+                self.upstream.write_str_all(&name)?;
+                self.upstream.write_str_all("{")?;
+                self.open.push(true);
+            }
+            None => self.open.push(false),
+        }
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        if self.open.pop() == Some(true) {
+            self.upstream.write_str_all("}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects a rendered document and writes it to an upstream `Render` as a fenced Markdown code
+/// block once `finish` is called. Annotations are ignored as code blocks can not be styled.
+///
+/// The fence is made longer than any run of backticks in the document so the document can never
+/// close the block early.
+///
+/// ```
+/// use pretty::{BoxDoc, Doc, FmtWrite, MarkdownCode};
+///
+/// let doc = Doc::<BoxDoc<()>>::text("let x = 1;");
+/// let mut s = String::new();
+/// {
+///     let mut out = MarkdownCode::new(FmtWrite::new(&mut s), "rust");
+///     doc.render_raw(80, &mut out).unwrap();
+///     out.finish().unwrap();
+/// }
+/// assert_eq!(s, "```rust\nlet x = 1;\n```");
+/// ```
+pub struct MarkdownCode<W> {
+    upstream: W,
+    language: String,
+    buffer: String,
+}
+
+impl<W> MarkdownCode<W> {
+    pub fn new<S: Into<String>>(upstream: W, language: S) -> MarkdownCode<W> {
+        MarkdownCode {
+            upstream,
+            language: language.into(),
+            buffer: String::new(),
+        }
+    }
+}
+
+impl<W> MarkdownCode<W>
+where
+    W: Render,
+{
+    /// Writes the code block to the upstream writer and returns it.
+    pub fn finish(mut self) -> Result<W, W::Error> {
+        let longest_run = self
+            .buffer
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(cmp::max(3, longest_run + 1));
+
+        self.upstream.write_str_all(&fence)?;
+        self.upstream.write_str_all(&self.language)?;
+        self.upstream.write_str_all("\n")?;
+        self.upstream.write_str_all(&self.buffer)?;
+        if !self.buffer.is_empty() && !self.buffer.ends_with('\n') {
+            self.upstream.write_str_all("\n")?;
+        }
+        self.upstream.write_str_all(&fence)?;
+        Ok(self.upstream)
+    }
+}
+
+impl<W> Render for MarkdownCode<W>
+where
+    W: Render,
+{
+    type Error = W::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<usize, W::Error> {
+        self.buffer.push_str(s);
+        Ok(s.len())
+    }
+}
+
+impl<A, W> RenderAnnotated<A> for MarkdownCode<W>
+where
+    W: Render,
+{
+    fn push_annotation(&mut self, _: &A) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Writes to an upstream `RenderAnnotated` until a maximum number of lines or bytes is reached,
+/// then writes an ellipsis and drops the rest of the document.
+///
+/// The ellipsis is written at the end of the last line which is kept, and is not counted against
+/// the maximum number of bytes.
+///
+/// ```
+/// use pretty::{BoxDoc, Doc, FmtWrite, Truncate};
+///
+/// let doc = Doc::<BoxDoc<()>>::intersperse((1..100).map(Doc::as_string), Doc::newline());
+/// let mut s = String::new();
+/// {
+///     let mut out = Truncate::new(FmtWrite::new(&mut s)).max_lines(3);
+///     doc.render_raw(80, &mut out).unwrap();
+///     assert!(out.is_truncated());
+/// }
+/// assert_eq!(s, "1\n2\n3…");
+/// ```
+pub struct Truncate<W> {
+    upstream: W,
+    max_lines: usize,
+    max_bytes: usize,
+    ellipsis: String,
+    lines: usize,
+    bytes: usize,
+    truncated: bool,
+    /// The number of annotations pushed after truncating, which are not written upstream.
+    dropped: usize,
+}
+
+impl<W> Truncate<W> {
+    /// Creates a writer without limits which writes `…` when it truncates.
+    pub fn new(upstream: W) -> Truncate<W> {
+        Truncate {
+            upstream,
+            max_lines: usize::MAX,
+            max_bytes: usize::MAX,
+            ellipsis: "…".to_string(),
+            lines: 1,
+            bytes: 0,
+            truncated: false,
+            dropped: 0,
+        }
+    }
+
+    /// Keeps at most `max_lines` lines. With 0 only the ellipsis is written.
+    pub fn max_lines(mut self, max_lines: usize) -> Truncate<W> {
+        self.max_lines = max_lines;
+        self
+    }
+
+    /// Keeps at most `max_bytes` bytes. With 0 only the ellipsis is written.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Truncate<W> {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets the text written where the document is cut off.
+    pub fn ellipsis<S: Into<String>>(mut self, ellipsis: S) -> Truncate<W> {
+        self.ellipsis = ellipsis.into();
+        self
+    }
+
+    /// Whether any part of the document was dropped.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn into_inner(self) -> W {
+        self.upstream
+    }
+}
+
+impl<W> Render for Truncate<W>
+where
+    W: Render,
+{
+    type Error = W::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<usize, W::Error> {
+        if self.truncated {
+            return Ok(s.len());
+        }
+
+        let mut end = s.len();
+        if end > self.max_bytes - self.bytes {
+            end = self.max_bytes - self.bytes;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.truncated = true;
+        }
+        if self.lines > self.max_lines {
+            // Not even the first line is kept
+            end = 0;
+            self.truncated |= !s.is_empty();
+        } else if let Some((i, _)) = s[..end]
+            .match_indices('\n')
+            .nth(self.max_lines - self.lines)
+        {
+            end = i;
+            self.truncated = true;
+        }
+        self.lines += s[..end].matches('\n').count();
+        self.bytes += end;
+
+        self.upstream.write_str_all(&s[..end])?;
+        if self.truncated {
+            self.upstream.write_str_all(&self.ellipsis)?;
+        }
+        Ok(s.len())
+    }
+}
+
+impl<A, W> RenderAnnotated<A> for Truncate<W>
+where
+    W: RenderAnnotated<A>,
+{
+    fn push_annotation(&mut self, annotation: &A) -> Result<(), Self::Error> {
+        if self.truncated {
+            self.dropped += 1;
+            return Ok(());
+        }
+        self.upstream.push_annotation(annotation)
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        if self.dropped != 0 {
+            self.dropped -= 1;
+            return Ok(());
+        }
+        self.upstream.pop_annotation()
+    }
+}
+
+/// Collects a rendered document and writes it to something implementing `std::io::Write` as JSON
+/// once `finish` is called.
+///
+/// The output is an array of lines where each line is an array of
+/// `{ "text": .., "annotation": .. }` segments, letting a front end style the text without
+/// performing the layout itself.
+///
+/// ```
+/// use pretty::{Doc, JsonWrite};
+///
+/// let doc = Doc::text("null").annotate("literal")
+///     .append(Doc::newline())
+///     .append(Doc::text("1"));
+/// let mut out = JsonWrite::new(Vec::new());
+/// doc.render_raw(80, &mut out).unwrap();
+/// let json = out.finish().unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(json).unwrap(),
+///     r#"[[{"text":"null","annotation":"literal"}],[{"text":"1","annotation":null}]]"#
+/// );
+/// ```
+#[cfg(feature = "serde")]
+pub struct JsonWrite<W, A> {
+    upstream: W,
+    lines: LineBuffer<A>,
+}
+
+#[cfg(feature = "serde")]
+impl<W, A> JsonWrite<W, A> {
+    pub fn new(upstream: W) -> JsonWrite<W, A> {
+        JsonWrite {
+            upstream,
+            lines: LineBuffer::new(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W, A> JsonWrite<W, A>
+where
+    W: io::Write,
+    A: Serialize,
+{
+    /// Writes the collected lines to the upstream writer and returns it.
+    pub fn finish(mut self) -> io::Result<W> {
+        serde_json::to_writer(&mut self.upstream, self.lines.lines())?;
+        Ok(self.upstream)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W, A> Render for JsonWrite<W, A>
+where
+    A: Clone,
+{
+    type Error = io::Error;
+
+    fn write_str(&mut self, s: &str) -> io::Result<usize> {
+        self.lines
+            .write_str(s)
+            .map_err(|_| io::ErrorKind::Other.into())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W, A> RenderAnnotated<A> for JsonWrite<W, A>
+where
+    A: Clone,
+{
+    fn push_annotation(&mut self, annotation: &A) -> Result<(), Self::Error> {
+        self.lines
+            .push_annotation(annotation)
+            .map_err(|_| io::ErrorKind::Other.into())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        RenderAnnotated::<A>::pop_annotation(&mut self.lines)
+            .map_err(|_| io::ErrorKind::Other.into())
+    }
+}
+
 #[cfg(feature = "termcolor")]
 pub struct TermColored<W> {
     color_stack: Vec<ColorSpec>,
@@ -157,8 +831,102 @@ where
     }
 }
 
+/// Chooses which foldable documents are replaced by their placeholders when rendering.
+///
+/// ```
+/// use pretty::{BoxDoc, Doc, Folding};
+///
+/// fn object<'a>(fields: Doc<'a, BoxDoc<'a, ()>>) -> Doc<'a, BoxDoc<'a, ()>> {
+///     Doc::text("{ ")
+///         .append(fields)
+///         .append(Doc::text(" }"))
+///         .foldable(Doc::text("{…}"))
+/// }
+/// let doc = object(Doc::text("a: ").append(object(Doc::text("b: 1"))));
+///
+/// assert_eq!(doc.pretty(80).to_string(), "{ a: { b: 1 } }");
+/// assert_eq!(doc.pretty(80).folded(Folding::new().depth(1)).to_string(), "{ a: {…} }");
+/// assert_eq!(doc.pretty(80).folded(Folding::new().nodes(1)).to_string(), "{…}");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Folding {
+    depth: usize,
+    nodes: usize,
+}
+
+impl Default for Folding {
+    fn default() -> Folding {
+        Folding::new()
+    }
+}
+
+impl Folding {
+    /// Does not fold anything.
+    pub fn new() -> Folding {
+        Folding {
+            depth: usize::MAX,
+            nodes: usize::MAX,
+        }
+    }
+
+    /// Folds the foldable documents nested in `depth` other foldable documents.
+    pub fn depth(mut self, depth: usize) -> Folding {
+        self.depth = depth;
+        self
+    }
+
+    /// Folds the foldable documents which contain more than `nodes` foldable documents,
+    /// counting themselves.
+    pub fn nodes(mut self, nodes: usize) -> Folding {
+        self.nodes = nodes;
+        self
+    }
+
+    /// Whether the foldable document `doc`, nested in `depth` unfolded foldable documents, is
+    /// replaced by its placeholder.
+    fn folds<'a, T, A>(&self, doc: &Doc<'a, T, A>, depth: usize) -> bool
+    where
+        T: Deref<Target = Doc<'a, T, A>>,
+    {
+        depth >= self.depth
+            || (self.nodes != usize::MAX && count_folds(doc, self.nodes) >= self.nodes)
+    }
+}
+
+/// Counts the foldable documents in `doc`, stopping once `limit` is exceeded.
+fn count_folds<'a, T, A>(doc: &Doc<'a, T, A>, limit: usize) -> usize
+where
+    T: Deref<Target = Doc<'a, T, A>>,
+{
+    match *doc {
+        Doc::Nil | Doc::Space | Doc::Newline | Doc::Text(_) | Doc::TabStop => 0,
+        Doc::Append(ref left, ref right) | Doc::IfBreak(ref left, ref right) => {
+            let count = count_folds(left, limit);
+            if count > limit {
+                count
+            } else {
+                count + count_folds(right, limit - count)
+            }
+        }
+        Doc::Fold(ref doc, _) => 1 + count_folds(doc, limit.saturating_sub(1)),
+        Doc::Group(ref doc)
+        | Doc::Break(ref doc)
+        | Doc::Nest(_, ref doc)
+        | Doc::Annotated(_, ref doc)
+        | Doc::Align(ref doc)
+        | Doc::Prefix(_, ref doc)
+        | Doc::AlignRight(ref doc)
+        | Doc::Center(ref doc) => count_folds(doc, limit),
+    }
+}
+
 #[inline]
-pub fn best<'a, W, T, A>(doc: &'a Doc<'a, T, A>, width: usize, out: &mut W) -> Result<(), W::Error>
+pub fn best<'a, W, T, A>(
+    doc: &'a Doc<'a, T, A>,
+    width: usize,
+    folding: Folding,
+    out: &mut W,
+) -> Result<(), W::Error>
 where
     T: Deref<Target = Doc<'a, T, A>>,
     W: ?Sized + RenderAnnotated<A>,
@@ -169,33 +937,56 @@ where
         Flat,
     }
 
-    type Cmd<'a, T, A> = (usize, Mode, &'a Doc<'a, T, A>);
+    /// The indentation of the lines inside a document: the prefixes they start with followed by
+    /// a number of spaces.
+    #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    struct Indent {
+        /// The index of the innermost prefix in the prefixes of `best`.
+        prefix: Option<usize>,
+        spaces: usize,
+        /// The number of unfolded foldable documents around the document.
+        folds: usize,
+    }
+
+    /// A prefix written after the indentation of the document it was added in.
+    struct Prefix<'a> {
+        indent: Indent,
+        text: &'a str,
+        /// The column the prefix ends at.
+        column: usize,
+    }
+
+    type Cmd<'a, T, A> = (Indent, Mode, &'a Doc<'a, T, A>);
 
-    fn write_newline<W>(ind: usize, out: &mut W) -> Result<(), W::Error>
+    fn column(ind: Indent, prefixes: &[Prefix]) -> usize {
+        ind.prefix.map_or(0, |i| prefixes[i].column) + ind.spaces
+    }
+
+    fn write_newline<'a, W, A>(
+        ind: Indent,
+        prefixes: &[Prefix<'a>],
+        out: &mut TabStops<'a, '_, W, A>,
+    ) -> Result<(), W::Error>
     where
-        W: ?Sized + Render,
+        W: ?Sized + RenderAnnotated<A>,
     {
-        out.write_str_all("\n")?;
-        write_spaces(ind, out)
+        out.newline()?;
+        write_indent(ind, prefixes, out)
     }
 
-    fn write_spaces<W>(spaces: usize, out: &mut W) -> Result<(), W::Error>
+    fn write_indent<'a, W, A>(
+        ind: Indent,
+        prefixes: &[Prefix<'a>],
+        out: &mut TabStops<'a, '_, W, A>,
+    ) -> Result<(), W::Error>
     where
-        W: ?Sized + Render,
+        W: ?Sized + RenderAnnotated<A>,
     {
-        macro_rules! make_spaces {
-            () => { "" };
-            ($s: tt $($t: tt)*) => { concat!("          ", make_spaces!($($t)*)) };
+        if let Some(i) = ind.prefix {
+            write_indent(prefixes[i].indent, prefixes, out)?;
+            out.text(prefixes[i].text)?;
         }
-
-        const SPACES: &str = make_spaces!(,,,,,,,,,,);
-        let mut inserted = 0;
-        while inserted < spaces {
-            let insert = cmp::min(SPACES.len(), spaces - inserted);
-            inserted += out.write_str(&SPACES[..insert])?;
-        }
-
-        Ok(())
+        out.spaces(ind.spaces)
     }
 
     #[inline]
@@ -203,6 +994,9 @@ where
         next: Cmd<'a, T, A>,
         bcmds: &[Cmd<'a, T, A>],
         fcmds: &mut Vec<Cmd<'a, T, A>>,
+        prefixes: &[Prefix],
+        width: usize,
+        folding: Folding,
         mut rem: isize,
     ) -> bool
     where
@@ -246,7 +1040,11 @@ where
                             Mode::Break => fcmds.push((ind, mode, doc)),
                         },
                         Doc::Nest(off, ref doc) => {
-                            fcmds.push((ind + off, mode, doc));
+                            let ind = Indent {
+                                spaces: ind.spaces + off,
+                                ..ind
+                            };
+                            fcmds.push((ind, mode, doc));
                         }
                         Doc::Space => match mode {
                             Mode::Flat => {
@@ -256,15 +1054,65 @@ where
                                 return true;
                             }
                         },
-                        Doc::Newline => return true,
+                        Doc::Newline => match mode {
+                            // A newline is written even when its group is flat, so the group is
+                            // only flat if each of its lines fits and not just the first one.
+                            // This keeps a group, such as a table, from being laid out flat when
+                            // one of its later lines is too wide.
+                            Mode::Flat => rem = width as isize - column(ind, prefixes) as isize,
+                            Mode::Break => return true,
+                        },
                         Doc::Text(ref str) => {
-                            rem -= str.len() as isize;
+                            // Line breaks in text are treated like `Newline`, except that the
+                            // lines after them start at the first column
+                            let mut lines = str.split('\n');
+                            rem -= lines.next().map_or(0, text_width) as isize;
+                            for line in lines {
+                                match mode {
+                                    Mode::Flat if rem >= 0 => {
+                                        rem = width as isize - text_width(line) as isize
+                                    }
+                                    _ => return rem >= 0,
+                                }
+                            }
                         }
                         Doc::Annotated(_, ref doc) => fcmds.push((ind, mode, doc)),
                         Doc::IfBreak(ref yes, ref no) => match mode {
                             Mode::Break => fcmds.push((ind, mode, yes)),
                             Mode::Flat => fcmds.push((ind, mode, no)),
                         },
+                        // Only the first line is measured so the indentation does not matter
+                        Doc::Align(ref doc) => fcmds.push((ind, mode, doc)),
+                        Doc::Prefix(ref prefix, ref doc) => {
+                            rem -= text_width(prefix) as isize;
+                            fcmds.push((ind, mode, doc));
+                        }
+                        Doc::TabStop => {}
+                        Doc::Fold(ref doc, ref placeholder) => {
+                            if folding.folds(doc, ind.folds) {
+                                fcmds.push((ind, mode, placeholder));
+                            } else {
+                                let ind = Indent {
+                                    folds: ind.folds + 1,
+                                    ..ind
+                                };
+                                fcmds.push((ind, mode, doc));
+                            }
+                        }
+                        // The padding fills the rest of the line, or half of it
+                        Doc::AlignRight(ref inner) | Doc::Center(ref inner) => {
+                            match flat_width(inner) {
+                                Some(width) if width as isize > rem => return false,
+                                Some(width) => {
+                                    let rest = rem - width as isize;
+                                    rem = match *doc {
+                                        Doc::Center(_) => rest - rest / 2,
+                                        _ => 0,
+                                    }
+                                }
+                                None => fcmds.push((ind, mode, inner)),
+                            }
+                        }
                     }
                 }
             }
@@ -274,9 +1122,16 @@ where
     }
 
     let mut pos = 0;
-    let mut bcmds = vec![(0, Mode::Break, doc)];
+    let mut prefixes = vec![];
+    let top = Indent {
+        prefix: None,
+        spaces: 0,
+        folds: 0,
+    };
+    let mut bcmds = vec![(top, Mode::Break, doc)];
     let mut fcmds = vec![];
     let mut annotation_levels = vec![];
+    let mut out = TabStops::new(out);
 
     while let Some((ind, mode, doc)) = bcmds.pop() {
         match *doc {
@@ -297,7 +1152,7 @@ where
                 Mode::Break => {
                     let next = (ind, Mode::Flat, &**doc);
                     let rem = width as isize - pos as isize;
-                    if fitting(next, &bcmds, &mut fcmds, rem) {
+                    if fitting(next, &bcmds, &mut fcmds, &prefixes, width, folding, rem) {
                         bcmds.push(next);
                     } else {
                         bcmds.push((ind, Mode::Break, doc));
@@ -313,20 +1168,26 @@ where
                 bcmds.push((ind, Mode::Break, doc));
             }
             Doc::Nest(off, ref doc) => {
-                bcmds.push((ind + off, mode, doc));
+                let ind = Indent {
+                    spaces: ind.spaces + off,
+                    ..ind
+                };
+                bcmds.push((ind, mode, doc));
             }
             Doc::Space => match mode {
+                // The column has to follow the space for groups after it to be measured from there
                 Mode::Flat => {
-                    write_spaces(1, out)?;
+                    out.spaces(1)?;
+                    pos += 1;
                 }
                 Mode::Break => {
-                    write_newline(ind, out)?;
-                    pos = ind;
+                    write_newline(ind, &prefixes, &mut out)?;
+                    pos = column(ind, &prefixes);
                 }
             },
             Doc::Newline => {
-                write_newline(ind, out)?;
-                pos = ind;
+                write_newline(ind, &prefixes, &mut out)?;
+                pos = column(ind, &prefixes);
 
                 // Since this newline caused an early break we don't know if the remaining
                 // documents fit the next line so recalculate if they fit
@@ -336,11 +1197,11 @@ where
                         .iter()
                         .rev()
                         .position(|t| t.1 == Mode::Break)
-                        .unwrap_or_else(|| bcmds.len());
+                        .unwrap_or(bcmds.len());
                 fcmds.extend_from_slice(&bcmds[docs..]);
                 if let Some(next) = fcmds.pop() {
                     let rem = width as isize - pos as isize;
-                    if !fitting(next, &bcmds, &mut fcmds, rem) {
+                    if !fitting(next, &bcmds, &mut fcmds, &prefixes, width, folding, rem) {
                         for &mut (_, ref mut mode, _) in &mut bcmds[docs..] {
                             *mode = Mode::Break;
                         }
@@ -348,8 +1209,11 @@ where
                 }
             }
             Doc::Text(ref s) => {
-                out.write_str_all(s)?;
-                pos += s.len();
+                out.text(s)?;
+                pos = match s.rfind('\n') {
+                    Some(i) => text_width(&s[i + 1..]),
+                    None => pos + text_width(s),
+                };
             }
             Doc::Annotated(ref ann, ref doc) => {
                 out.push_annotation(ann)?;
@@ -360,13 +1224,268 @@ where
                 Mode::Break => bcmds.push((ind, mode, yes)),
                 Mode::Flat => bcmds.push((ind, mode, no)),
             },
+            Doc::Align(ref doc) => {
+                // The prefixes are kept, only the spaces after them change
+                let ind = Indent {
+                    spaces: pos.saturating_sub(ind.prefix.map_or(0, |i| prefixes[i].column)),
+                    ..ind
+                };
+                bcmds.push((ind, mode, doc));
+            }
+            Doc::Prefix(ref prefix, ref doc) => {
+                out.text(prefix)?;
+                pos += text_width(prefix);
+                prefixes.push(Prefix {
+                    indent: ind,
+                    text: prefix,
+                    column: column(ind, &prefixes) + text_width(prefix),
+                });
+                let ind = Indent {
+                    prefix: Some(prefixes.len() - 1),
+                    spaces: 0,
+                    ..ind
+                };
+                bcmds.push((ind, mode, doc));
+            }
+            Doc::AlignRight(ref inner) | Doc::Center(ref inner) => match flat_width(inner) {
+                // The document is laid out flat since its width is only known then
+                Some(inner_width) => {
+                    let mut padding = width.saturating_sub(pos + inner_width);
+                    if let Doc::Center(_) = *doc {
+                        padding /= 2;
+                    }
+                    out.spaces(padding)?;
+                    pos += padding;
+                    bcmds.push((ind, Mode::Flat, inner));
+                }
+                None => bcmds.push((ind, mode, inner)),
+            },
+            Doc::Fold(ref doc, ref placeholder) => {
+                if folding.folds(doc, ind.folds) {
+                    bcmds.push((ind, mode, placeholder));
+                } else {
+                    let ind = Indent {
+                        folds: ind.folds + 1,
+                        ..ind
+                    };
+                    bcmds.push((ind, mode, doc));
+                }
+            }
+            // Stops in a flat document are on a single line, there is nothing to align them with
+            Doc::TabStop => {
+                if mode == Mode::Break {
+                    out.tab_stop(pos);
+                }
+            }
         }
 
-        if annotation_levels.last() == Some(&bcmds.len()) {
+        // Annotations directly around each other end at the same time
+        while annotation_levels.last() == Some(&bcmds.len()) {
             annotation_levels.pop();
             out.pop_annotation()?;
         }
     }
 
+    out.flush()
+}
+
+fn write_spaces<W>(spaces: usize, out: &mut W) -> Result<(), W::Error>
+where
+    W: ?Sized + Render,
+{
+    macro_rules! make_spaces {
+        () => { "" };
+        ($s: tt $($t: tt)*) => { concat!("          ", make_spaces!($($t)*)) };
+    }
+
+    const SPACES: &str = make_spaces!(,,,,,,,,,,);
+    let mut inserted = 0;
+    while inserted < spaces {
+        let insert = cmp::min(SPACES.len(), spaces - inserted);
+        inserted += out.write_str(&SPACES[..insert])?;
+    }
+
     Ok(())
 }
+
+/// Output written to a line holding tab stops, which is kept until the stops can be aligned.
+enum Buffered<'a, A: 'a> {
+    Text(&'a str),
+    Spaces(usize),
+    PushAnnotation(&'a A),
+    PopAnnotation,
+    TabStop,
+}
+
+/// A line holding tab stops, from its first tab stop on.
+struct BufferedLine<'a, A: 'a> {
+    output: Vec<Buffered<'a, A>>,
+    /// The column of each tab stop before it is aligned.
+    stops: Vec<usize>,
+}
+
+/// The output of `best`, which passes everything on to `out` except for consecutive lines holding
+/// tab stops. These are kept until the block of lines ends, and the `n`th stops of its lines are
+/// then padded to the same column.
+struct TabStops<'a, 'w, W, A: 'a>
+where
+    W: ?Sized + 'w,
+{
+    out: &'w mut W,
+    block: Vec<BufferedLine<'a, A>>,
+}
+
+impl<'a, 'w, W, A> TabStops<'a, 'w, W, A>
+where
+    W: ?Sized + RenderAnnotated<A>,
+{
+    fn new(out: &'w mut W) -> TabStops<'a, 'w, W, A> {
+        TabStops {
+            out,
+            block: Vec::new(),
+        }
+    }
+
+    fn buffer(&mut self, output: Buffered<'a, A>) -> bool {
+        match self.block.last_mut() {
+            Some(line) => {
+                line.output.push(output);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn text(&mut self, text: &'a str) -> Result<(), W::Error> {
+        if self.buffer(Buffered::Text(text)) {
+            return Ok(());
+        }
+        self.out.write_str_all(text)
+    }
+
+    fn spaces(&mut self, spaces: usize) -> Result<(), W::Error> {
+        if self.buffer(Buffered::Spaces(spaces)) {
+            return Ok(());
+        }
+        write_spaces(spaces, self.out)
+    }
+
+    fn push_annotation(&mut self, annotation: &'a A) -> Result<(), W::Error> {
+        if self.buffer(Buffered::PushAnnotation(annotation)) {
+            return Ok(());
+        }
+        self.out.push_annotation(annotation)
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), W::Error> {
+        if self.buffer(Buffered::PopAnnotation) {
+            return Ok(());
+        }
+        self.out.pop_annotation()
+    }
+
+    fn tab_stop(&mut self, column: usize) {
+        if self.block.is_empty() {
+            self.block.push(BufferedLine {
+                output: Vec::new(),
+                stops: Vec::new(),
+            });
+        }
+        let line = self.block.last_mut().expect("a line was just pushed");
+        line.output.push(Buffered::TabStop);
+        line.stops.push(column);
+    }
+
+    fn newline(&mut self) -> Result<(), W::Error> {
+        match self.block.last() {
+            None => self.out.write_str_all("\n"),
+            Some(line) if line.stops.is_empty() => {
+                self.flush()?;
+                self.out.write_str_all("\n")
+            }
+            Some(_) => {
+                self.block.push(BufferedLine {
+                    output: Vec::new(),
+                    stops: Vec::new(),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the block of lines with their tab stops aligned.
+    fn flush(&mut self) -> Result<(), W::Error> {
+        let block = mem::take(&mut self.block);
+
+        // Padding a stop moves the stops after it on the same line
+        let mut padding = vec![Vec::new(); block.len()];
+        let mut shift = vec![0; block.len()];
+        let stops = block.iter().map(|line| line.stops.len()).max().unwrap_or(0);
+        for n in 0..stops {
+            let column = block
+                .iter()
+                .zip(&shift)
+                .filter_map(|(line, shift)| line.stops.get(n).map(|column| column + shift))
+                .max()
+                .unwrap_or(0);
+            for (i, line) in block.iter().enumerate() {
+                if let Some(stop) = line.stops.get(n) {
+                    padding[i].push(column - (stop + shift[i]));
+                    shift[i] = column - stop;
+                }
+            }
+        }
+
+        for (i, line) in block.into_iter().enumerate() {
+            if i != 0 {
+                self.out.write_str_all("\n")?;
+            }
+            let mut padding = padding[i].iter();
+            for output in line.output {
+                match output {
+                    Buffered::Text(text) => self.out.write_str_all(text)?,
+                    Buffered::Spaces(spaces) => write_spaces(spaces, self.out)?,
+                    Buffered::PushAnnotation(annotation) => self.out.push_annotation(annotation)?,
+                    Buffered::PopAnnotation => self.out.pop_annotation()?,
+                    Buffered::TabStop => {
+                        write_spaces(*padding.next().expect("one padding per stop"), self.out)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of columns `text` takes up, which is its number of `char`s.
+///
+/// Every width is measured this way, so text is aligned correctly as long as each of its `char`s
+/// is a single column wide. This holds for most scripts and for box-drawing characters, but not
+/// for combining characters or wide characters such as CJK ideographs and emoji.
+pub(crate) fn text_width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// The width of `doc` when it is laid out on a single line, or `None` if it always breaks.
+pub(crate) fn flat_width<'a, T, A>(doc: &Doc<'a, T, A>) -> Option<usize>
+where
+    T: Deref<Target = Doc<'a, T, A>>,
+{
+    match *doc {
+        Doc::Nil | Doc::TabStop => Some(0),
+        Doc::Append(ref left, ref right) => Some(flat_width(left)? + flat_width(right)?),
+        Doc::Break(_) | Doc::Newline => None,
+        Doc::Space => Some(1),
+        Doc::Text(ref text) if text.contains('\n') => None,
+        Doc::Text(ref text) => Some(text_width(text)),
+        Doc::Prefix(ref prefix, ref doc) => Some(text_width(prefix) + flat_width(doc)?),
+        Doc::IfBreak(_, ref doc) => flat_width(doc),
+        Doc::Group(ref doc)
+        | Doc::Nest(_, ref doc)
+        | Doc::Fold(ref doc, _)
+        | Doc::AlignRight(ref doc)
+        | Doc::Center(ref doc)
+        | Doc::Annotated(_, ref doc)
+        | Doc::Align(ref doc) => flat_width(doc),
+    }
+}
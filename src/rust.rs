@@ -0,0 +1,1727 @@
+//! Formatting of Rust source code parsed with [`syn`](https://docs.rs/syn/1).
+//!
+//! The layout follows the conventions of `rustfmt`: blocks are indented by 4 spaces, argument
+//! lists and struct literals are put on one line when they fit and otherwise get one element per
+//! line followed by a trailing comma, and method chains with several calls are broken before each
+//! `.` when they do not fit. Nodes which are not supported are printed from their tokens.
+//!
+//! This module is only available with the `syn` feature.
+//!
+//! ```
+//! # extern crate pretty;
+//! # extern crate syn;
+//! use pretty::{rust, BoxAllocator, BoxDoc, Doc};
+//!
+//! # fn main() {
+//! let file: syn::File = syn::parse_str(
+//!     "fn total(items: &[Item]) -> u32 { items.iter().filter(|item| item.active).map(|item| item.price).sum() }",
+//! )
+//! .unwrap();
+//! let doc: Doc<BoxDoc<()>> = rust::file(&BoxAllocator, &file).into();
+//!
+//! assert_eq!(
+//!     doc.pretty(60).to_string(),
+//!     "fn total(items: &[Item]) -> u32 {
+//!     items
+//!         .iter()
+//!         .filter(|item| item.active)
+//!         .map(|item| item.price)
+//!         .sum()
+//! }"
+//! );
+//! # }
+//! ```
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+    Attribute, BinOp, Block, Expr, Fields, FnArg, GenericArgument, GenericParam, Generics,
+    ImplItem, Item, Macro, MacroDelimiter, Member, Meta, NestedMeta, Pat, Path, PathArguments,
+    RangeLimits, ReturnType, Signature, Stmt, TraitItem, Type, TypeParamBound, UnOp, UseTree,
+    Visibility, WherePredicate,
+};
+
+use value::{self, Pretty, INDENT};
+use {DocAllocator, DocBuilder};
+
+/// Formats a source file, separating its items by blank lines.
+pub fn file<'a, D, A>(allocator: &'a D, file: &syn::File) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    contents(allocator, &file.attrs, &file.items)
+}
+
+/// Formats an item together with its attributes.
+pub fn item<'a, D, A>(allocator: &'a D, item: &Item) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *item {
+        Item::Fn(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(function(
+                allocator,
+                &item.attrs,
+                &item.sig,
+                Some(&item.block),
+            )),
+        Item::Struct(ref item) => {
+            let header = attributes(allocator, &item.attrs)
+                .append(visibility(allocator, &item.vis))
+                .append(allocator.text("struct "))
+                .append(allocator.as_string(&item.ident))
+                .append(generics(allocator, &item.generics));
+            match item.fields {
+                Fields::Named(ref fields) => header
+                    .append(where_clause_open(allocator, &item.generics))
+                    .append(braces(
+                        allocator,
+                        fields.named.iter().map(|field| {
+                            struct_field(allocator, field).append(allocator.text(","))
+                        }),
+                        fields.named.is_empty(),
+                    )),
+                Fields::Unnamed(_) => header
+                    .append(struct_fields(allocator, &item.fields))
+                    .append(where_clause(allocator, &item.generics, false))
+                    .append(allocator.text(";")),
+                Fields::Unit => header
+                    .append(where_clause(allocator, &item.generics, false))
+                    .append(allocator.text(";")),
+            }
+        }
+        Item::Enum(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("enum "))
+            .append(allocator.as_string(&item.ident))
+            .append(generics(allocator, &item.generics))
+            .append(where_clause_open(allocator, &item.generics))
+            .append(braces(
+                allocator,
+                item.variants.iter().map(|variant| {
+                    let mut doc = attributes(allocator, &variant.attrs)
+                        .append(allocator.as_string(&variant.ident))
+                        .append(struct_fields(allocator, &variant.fields));
+                    if let Some((_, ref discriminant)) = variant.discriminant {
+                        doc = doc
+                            .append(allocator.text(" = "))
+                            .append(expr(allocator, discriminant));
+                    }
+                    doc.append(allocator.text(","))
+                }),
+                item.variants.is_empty(),
+            )),
+        Item::Use(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("use "))
+            .append(if item.leading_colon.is_some() {
+                allocator.text("::")
+            } else {
+                allocator.nil()
+            })
+            .append(use_tree(allocator, &item.tree))
+            .append(allocator.text(";")),
+        Item::Const(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("const "))
+            .append(allocator.as_string(&item.ident))
+            .append(allocator.text(": "))
+            .append(ty(allocator, &item.ty))
+            .append(allocator.text(" = "))
+            .append(expr(allocator, &item.expr))
+            .append(allocator.text(";")),
+        Item::Static(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("static "))
+            .append(if item.mutability.is_some() {
+                allocator.text("mut ")
+            } else {
+                allocator.nil()
+            })
+            .append(allocator.as_string(&item.ident))
+            .append(allocator.text(": "))
+            .append(ty(allocator, &item.ty))
+            .append(allocator.text(" = "))
+            .append(expr(allocator, &item.expr))
+            .append(allocator.text(";")),
+        Item::Type(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("type "))
+            .append(allocator.as_string(&item.ident))
+            .append(generics(allocator, &item.generics))
+            .append(where_clause(allocator, &item.generics, true))
+            .append(allocator.text(" = "))
+            .append(ty(allocator, &item.ty))
+            .append(allocator.text(";")),
+        Item::Impl(ref item) => {
+            let mut doc = attributes(allocator, &item.attrs);
+            if item.unsafety.is_some() {
+                doc = doc.append(allocator.text("unsafe "));
+            }
+            doc = doc
+                .append(allocator.text("impl"))
+                .append(generics(allocator, &item.generics))
+                .append(allocator.text(" "));
+            if let Some((ref bang, ref trait_, _)) = item.trait_ {
+                if bang.is_some() {
+                    doc = doc.append(allocator.text("!"));
+                }
+                doc = doc
+                    .append(path(allocator, trait_))
+                    .append(allocator.text(" for "));
+            }
+            doc.append(ty(allocator, &item.self_ty))
+                .append(where_clause_open(allocator, &item.generics))
+                .append(items_braces(
+                    allocator,
+                    &item.attrs,
+                    item.items.iter().map(|item| impl_item(allocator, item)),
+                ))
+        }
+        Item::Trait(ref item) => {
+            let mut doc =
+                attributes(allocator, &item.attrs).append(visibility(allocator, &item.vis));
+            if item.unsafety.is_some() {
+                doc = doc.append(allocator.text("unsafe "));
+            }
+            if item.auto_token.is_some() {
+                doc = doc.append(allocator.text("auto "));
+            }
+            doc = doc
+                .append(allocator.text("trait "))
+                .append(allocator.as_string(&item.ident))
+                .append(generics(allocator, &item.generics));
+            if !item.supertraits.is_empty() {
+                doc = doc
+                    .append(allocator.text(": "))
+                    .append(bounds(allocator, &item.supertraits));
+            }
+            doc.append(where_clause_open(allocator, &item.generics))
+                .append(items_braces(
+                    allocator,
+                    &item.attrs,
+                    item.items.iter().map(|item| trait_item(allocator, item)),
+                ))
+        }
+        Item::Mod(ref item) => {
+            let doc = attributes(allocator, &item.attrs)
+                .append(visibility(allocator, &item.vis))
+                .append(allocator.text("mod "))
+                .append(allocator.as_string(&item.ident));
+            match item.content {
+                Some((_, ref content)) => doc.append(allocator.text(" ")).append(braces(
+                    allocator,
+                    Some(contents(allocator, &item.attrs, content)),
+                    content.is_empty() && item.attrs.iter().all(is_outer),
+                )),
+                None => doc.append(allocator.text(";")),
+            }
+        }
+        Item::Macro(ref item) => match item.ident {
+            // The rules of `macro_rules!` definitions are printed from their tokens
+            Some(ref ident) => attributes(allocator, &item.attrs)
+                .append(path(allocator, &item.mac.path))
+                .append(allocator.text("! "))
+                .append(allocator.as_string(ident))
+                .append(allocator.text(" { "))
+                .append(tokens(allocator, &item.mac.tokens))
+                .append(allocator.text(" }")),
+            None => attributes(allocator, &item.attrs)
+                .append(macro_call(allocator, &item.mac))
+                .append(if item.semi_token.is_some() {
+                    allocator.text(";")
+                } else {
+                    allocator.nil()
+                }),
+        },
+        _ => tokens(allocator, item),
+    }
+}
+
+/// Formats a statement.
+pub fn stmt<'a, D, A>(allocator: &'a D, stmt: &Stmt) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *stmt {
+        Stmt::Local(ref local) => {
+            let mut doc = attributes(allocator, &local.attrs)
+                .append(allocator.text("let "))
+                .append(pat(allocator, &local.pat));
+            if let Some((_, ref init)) = local.init {
+                doc = doc
+                    .append(allocator.text(" = "))
+                    .append(expr(allocator, init));
+            }
+            doc.append(allocator.text(";"))
+        }
+        Stmt::Item(ref i) => item(allocator, i),
+        Stmt::Expr(ref e) => expr(allocator, e),
+        Stmt::Semi(ref e, _) => expr(allocator, e).append(allocator.text(";")),
+    }
+}
+
+/// Formats an expression.
+pub fn expr<'a, D, A>(allocator: &'a D, expr: &Expr) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    // `expr` is shadowed by the parameter
+    use self::expr as to_doc;
+
+    match *expr {
+        Expr::Lit(ref e) => tokens(allocator, &e.lit),
+        Expr::Path(ref e) if e.qself.is_none() => path(allocator, &e.path),
+        Expr::Call(ref e) => to_doc(allocator, &e.func).append(arguments(allocator, &e.args)),
+        Expr::MethodCall(_) | Expr::Field(_) | Expr::Try(_) | Expr::Await(_) => {
+            chain(allocator, expr)
+        }
+        Expr::Binary(_) => binary(allocator, expr),
+        Expr::Unary(ref e) => {
+            let op = match e.op {
+                UnOp::Deref(_) => "*",
+                UnOp::Not(_) => "!",
+                UnOp::Neg(_) => "-",
+            };
+            allocator.text(op).append(to_doc(allocator, &e.expr))
+        }
+        Expr::Reference(ref e) => allocator
+            .text(if e.mutability.is_some() { "&mut " } else { "&" })
+            .append(to_doc(allocator, &e.expr)),
+        Expr::Paren(ref e) => allocator
+            .text("(")
+            .append(to_doc(allocator, &e.expr))
+            .append(allocator.text(")")),
+        Expr::Group(ref e) => to_doc(allocator, &e.expr),
+        Expr::Tuple(ref e) => tuple(allocator, e.elems.iter().map(|e| to_doc(allocator, e))),
+        Expr::Array(ref e) => value::delimited(
+            allocator,
+            "[",
+            e.elems.iter().map(|e| to_doc(allocator, e)),
+            "]",
+        ),
+        Expr::Repeat(ref e) => allocator
+            .text("[")
+            .append(to_doc(allocator, &e.expr))
+            .append(allocator.text("; "))
+            .append(to_doc(allocator, &e.len))
+            .append(allocator.text("]")),
+        Expr::Struct(ref e) => {
+            let mut fields = e
+                .fields
+                .iter()
+                .map(|field| {
+                    let value = to_doc(allocator, &field.expr);
+                    if field.colon_token.is_some() {
+                        member(allocator, &field.member)
+                            .append(allocator.text(": "))
+                            .append(value)
+                    } else {
+                        value
+                    }
+                })
+                .collect::<Vec<_>>();
+            if let Some(ref rest) = e.rest {
+                fields.push(allocator.text("..").append(to_doc(allocator, rest)));
+            }
+            path(allocator, &e.path)
+                .append(allocator.text(" "))
+                .append(value::block(
+                    allocator,
+                    "{",
+                    fields,
+                    "}",
+                    INDENT,
+                    true,
+                    e.rest.is_none(),
+                ))
+        }
+        Expr::Block(ref e) => label(allocator, &e.label).append(block(allocator, &e.block)),
+        Expr::Unsafe(ref e) => {
+            allocator
+                .text("unsafe ")
+                .append(match inline_block(allocator, &e.block) {
+                    Some(doc) => doc.group(),
+                    None => block(allocator, &e.block),
+                })
+        }
+        Expr::If(ref e) => if_else(allocator, e),
+        Expr::Match(ref e) => allocator
+            .text("match ")
+            .append(to_doc(allocator, &e.expr))
+            .append(allocator.text(" "))
+            .append(braces(
+                allocator,
+                e.arms.iter().map(|arm| {
+                    let mut doc =
+                        attributes(allocator, &arm.attrs).append(pat(allocator, &arm.pat));
+                    if let Some((_, ref guard)) = arm.guard {
+                        doc = doc
+                            .append(allocator.text(" if "))
+                            .append(to_doc(allocator, guard));
+                    }
+                    doc = doc
+                        .append(allocator.text(" => "))
+                        .append(to_doc(allocator, &arm.body));
+                    match *arm.body {
+                        Expr::Block(_) => doc,
+                        _ => doc.append(allocator.text(",")),
+                    }
+                }),
+                e.arms.is_empty(),
+            )),
+        Expr::Closure(ref e) => {
+            let mut doc = allocator.nil();
+            if e.asyncness.is_some() {
+                doc = doc.append(allocator.text("async "));
+            }
+            if e.capture.is_some() {
+                doc = doc.append(allocator.text("move "));
+            }
+            doc = doc
+                .append(allocator.text("|"))
+                .append(join(
+                    allocator,
+                    e.inputs.iter().map(|input| pat(allocator, input)),
+                    ", ",
+                ))
+                .append(allocator.text("| "));
+            if let ReturnType::Type(_, ref output) = e.output {
+                doc = doc
+                    .append(allocator.text("-> "))
+                    .append(ty(allocator, output))
+                    .append(allocator.text(" "));
+            }
+            match *e.body {
+                Expr::Block(ref body) if body.label.is_none() => {
+                    match inline_block(allocator, &body.block) {
+                        Some(body) => doc.append(body.group()),
+                        None => doc.append(block(allocator, &body.block)),
+                    }
+                }
+                _ => doc.append(to_doc(allocator, &e.body)),
+            }
+        }
+        Expr::Return(ref e) => {
+            let doc = allocator.text("return");
+            match e.expr {
+                Some(ref e) => doc.append(allocator.text(" ")).append(to_doc(allocator, e)),
+                None => doc,
+            }
+        }
+        Expr::Break(ref e) => {
+            let mut doc = allocator.text("break");
+            if let Some(ref label) = e.label {
+                doc = doc
+                    .append(allocator.text(" "))
+                    .append(allocator.as_string(label));
+            }
+            match e.expr {
+                Some(ref e) => doc.append(allocator.text(" ")).append(to_doc(allocator, e)),
+                None => doc,
+            }
+        }
+        Expr::Continue(ref e) => match e.label {
+            Some(ref label) => allocator
+                .text("continue ")
+                .append(allocator.as_string(label)),
+            None => allocator.text("continue"),
+        },
+        Expr::Assign(ref e) => to_doc(allocator, &e.left)
+            .append(allocator.text(" = "))
+            .append(to_doc(allocator, &e.right)),
+        Expr::AssignOp(ref e) => to_doc(allocator, &e.left)
+            .append(allocator.text(" "))
+            .append(binary_op(allocator, &e.op))
+            .append(allocator.text(" "))
+            .append(to_doc(allocator, &e.right)),
+        Expr::Range(ref e) => {
+            let mut doc = allocator.nil();
+            if let Some(ref from) = e.from {
+                doc = doc.append(to_doc(allocator, from));
+            }
+            doc = doc.append(allocator.text(match e.limits {
+                RangeLimits::HalfOpen(_) => "..",
+                RangeLimits::Closed(_) => "..=",
+            }));
+            if let Some(ref to) = e.to {
+                doc = doc.append(to_doc(allocator, to));
+            }
+            doc
+        }
+        Expr::Cast(ref e) => to_doc(allocator, &e.expr)
+            .append(allocator.text(" as "))
+            .append(ty(allocator, &e.ty)),
+        Expr::Index(ref e) => to_doc(allocator, &e.expr)
+            .append(allocator.text("["))
+            .append(to_doc(allocator, &e.index))
+            .append(allocator.text("]")),
+        Expr::Let(ref e) => allocator
+            .text("let ")
+            .append(pat(allocator, &e.pat))
+            .append(allocator.text(" = "))
+            .append(to_doc(allocator, &e.expr)),
+        Expr::ForLoop(ref e) => label(allocator, &e.label)
+            .append(allocator.text("for "))
+            .append(pat(allocator, &e.pat))
+            .append(allocator.text(" in "))
+            .append(to_doc(allocator, &e.expr))
+            .append(allocator.text(" "))
+            .append(block(allocator, &e.body)),
+        Expr::While(ref e) => label(allocator, &e.label)
+            .append(allocator.text("while "))
+            .append(to_doc(allocator, &e.cond))
+            .append(allocator.text(" "))
+            .append(block(allocator, &e.body)),
+        Expr::Loop(ref e) => label(allocator, &e.label)
+            .append(allocator.text("loop "))
+            .append(block(allocator, &e.body)),
+        Expr::Macro(ref e) => macro_call(allocator, &e.mac),
+        _ => tokens(allocator, expr),
+    }
+}
+
+/// Formats a type.
+pub fn ty<'a, D, A>(allocator: &'a D, ty: &Type) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    // `ty` is shadowed by the parameter
+    use self::ty as to_doc;
+
+    match *ty {
+        Type::Path(ref t) if t.qself.is_none() => path(allocator, &t.path),
+        Type::Reference(ref t) => {
+            let mut doc = allocator.text("&");
+            if let Some(ref lifetime) = t.lifetime {
+                doc = doc
+                    .append(allocator.as_string(lifetime))
+                    .append(allocator.text(" "));
+            }
+            if t.mutability.is_some() {
+                doc = doc.append(allocator.text("mut "));
+            }
+            doc.append(to_doc(allocator, &t.elem))
+        }
+        Type::Ptr(ref t) => allocator
+            .text(if t.mutability.is_some() {
+                "*mut "
+            } else {
+                "*const "
+            })
+            .append(to_doc(allocator, &t.elem)),
+        Type::Slice(ref t) => allocator
+            .text("[")
+            .append(to_doc(allocator, &t.elem))
+            .append(allocator.text("]")),
+        Type::Array(ref t) => allocator
+            .text("[")
+            .append(to_doc(allocator, &t.elem))
+            .append(allocator.text("; "))
+            .append(expr(allocator, &t.len))
+            .append(allocator.text("]")),
+        Type::Tuple(ref t) => tuple(allocator, t.elems.iter().map(|t| to_doc(allocator, t))),
+        Type::Never(_) => allocator.text("!"),
+        Type::Infer(_) => allocator.text("_"),
+        Type::ImplTrait(ref t) => allocator.text("impl ").append(bounds(allocator, &t.bounds)),
+        Type::TraitObject(ref t) => allocator
+            .text(if t.dyn_token.is_some() { "dyn " } else { "" })
+            .append(bounds(allocator, &t.bounds)),
+        Type::Paren(ref t) => allocator
+            .text("(")
+            .append(to_doc(allocator, &t.elem))
+            .append(allocator.text(")")),
+        Type::Group(ref t) => to_doc(allocator, &t.elem),
+        _ => tokens(allocator, ty),
+    }
+}
+
+/// Formats a pattern.
+pub fn pat<'a, D, A>(allocator: &'a D, pat: &Pat) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    // `pat` is shadowed by the parameter
+    use self::pat as to_doc;
+
+    match *pat {
+        Pat::Ident(ref p) => {
+            let mut doc = allocator.nil();
+            if p.by_ref.is_some() {
+                doc = doc.append(allocator.text("ref "));
+            }
+            if p.mutability.is_some() {
+                doc = doc.append(allocator.text("mut "));
+            }
+            doc = doc.append(allocator.as_string(&p.ident));
+            match p.subpat {
+                Some((_, ref subpat)) => doc
+                    .append(allocator.text(" @ "))
+                    .append(to_doc(allocator, subpat)),
+                None => doc,
+            }
+        }
+        Pat::Wild(_) => allocator.text("_"),
+        Pat::Rest(_) => allocator.text(".."),
+        Pat::Tuple(ref p) => tuple(allocator, p.elems.iter().map(|p| to_doc(allocator, p))),
+        Pat::TupleStruct(ref p) => path(allocator, &p.path).append(value::delimited(
+            allocator,
+            "(",
+            p.pat.elems.iter().map(|p| to_doc(allocator, p)),
+            ")",
+        )),
+        Pat::Struct(ref p) => {
+            let mut fields = p
+                .fields
+                .iter()
+                .map(|field| {
+                    let value = to_doc(allocator, &field.pat);
+                    if field.colon_token.is_some() {
+                        member(allocator, &field.member)
+                            .append(allocator.text(": "))
+                            .append(value)
+                    } else {
+                        value
+                    }
+                })
+                .collect::<Vec<_>>();
+            if p.dot2_token.is_some() {
+                fields.push(allocator.text(".."));
+            }
+            path(allocator, &p.path)
+                .append(allocator.text(" "))
+                .append(value::block(
+                    allocator,
+                    "{",
+                    fields,
+                    "}",
+                    INDENT,
+                    true,
+                    p.dot2_token.is_none(),
+                ))
+        }
+        Pat::Path(ref p) if p.qself.is_none() => path(allocator, &p.path),
+        Pat::Lit(ref p) => expr(allocator, &p.expr),
+        Pat::Reference(ref p) => allocator
+            .text(if p.mutability.is_some() { "&mut " } else { "&" })
+            .append(to_doc(allocator, &p.pat)),
+        Pat::Or(ref p) => join(
+            allocator,
+            p.cases.iter().map(|p| to_doc(allocator, p)),
+            " | ",
+        ),
+        Pat::Type(ref p) => to_doc(allocator, &p.pat)
+            .append(allocator.text(": "))
+            .append(ty(allocator, &p.ty)),
+        Pat::Slice(ref p) => value::delimited(
+            allocator,
+            "[",
+            p.elems.iter().map(|p| to_doc(allocator, p)),
+            "]",
+        ),
+        _ => tokens(allocator, pat),
+    }
+}
+
+macro_rules! impl_pretty {
+    ($($ty:ty => $f:ident),*) => {$(
+        impl<'a, D, A> Pretty<'a, D, A> for $ty
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                $f(allocator, self)
+            }
+        }
+    )*};
+}
+
+impl_pretty! {
+    syn::File => file, Item => item, Stmt => stmt, Expr => expr, Type => ty, Pat => pat
+}
+
+fn items<'a, D, A>(allocator: &'a D, items: &[Item]) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = allocator.nil();
+    let mut previous: Option<&Item> = None;
+    for current in items {
+        if let Some(previous) = previous {
+            // Runs of imports are kept together, other items are separated by a blank line
+            doc = match (previous, current) {
+                (&Item::Use(_), &Item::Use(_)) | (&Item::ExternCrate(_), &Item::ExternCrate(_)) => {
+                    doc.append(allocator.newline())
+                }
+                _ => doc.append(blank_line(allocator)),
+            };
+        }
+        doc = doc.append(item(allocator, current));
+        previous = Some(current);
+    }
+    doc
+}
+
+/// Formats the inner attributes among `attrs` followed by `items`, as in a file or module.
+fn contents<'a, D, A>(allocator: &'a D, attrs: &[Attribute], items: &[Item]) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match inner_attributes(allocator, attrs) {
+        Some(attrs) if items.is_empty() => attrs,
+        Some(attrs) => attrs
+            .append(blank_line(allocator))
+            .append(self::items(allocator, items)),
+        None => self::items(allocator, items),
+    }
+}
+
+/// Places the inner attributes among `attrs` and the items of an `impl` or trait between braces,
+/// separated by blank lines.
+fn items_braces<'a, D, A, I>(
+    allocator: &'a D,
+    attrs: &[Attribute],
+    items: I,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = DocBuilder<'a, D, A>>,
+{
+    let mut docs = inner_attributes(allocator, attrs)
+        .into_iter()
+        .chain(items)
+        .peekable();
+    let empty = docs.peek().is_none();
+    braces(allocator, separated(allocator, docs, true), empty)
+}
+
+fn impl_item<'a, D, A>(allocator: &'a D, item: &ImplItem) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *item {
+        ImplItem::Method(ref item) => {
+            let mut doc =
+                attributes(allocator, &item.attrs).append(visibility(allocator, &item.vis));
+            if item.defaultness.is_some() {
+                doc = doc.append(allocator.text("default "));
+            }
+            doc.append(function(
+                allocator,
+                &item.attrs,
+                &item.sig,
+                Some(&item.block),
+            ))
+        }
+        ImplItem::Const(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("const "))
+            .append(allocator.as_string(&item.ident))
+            .append(allocator.text(": "))
+            .append(ty(allocator, &item.ty))
+            .append(allocator.text(" = "))
+            .append(expr(allocator, &item.expr))
+            .append(allocator.text(";")),
+        ImplItem::Type(ref item) => attributes(allocator, &item.attrs)
+            .append(visibility(allocator, &item.vis))
+            .append(allocator.text("type "))
+            .append(allocator.as_string(&item.ident))
+            .append(generics(allocator, &item.generics))
+            .append(allocator.text(" = "))
+            .append(ty(allocator, &item.ty))
+            .append(allocator.text(";")),
+        _ => tokens(allocator, item),
+    }
+}
+
+fn trait_item<'a, D, A>(allocator: &'a D, item: &TraitItem) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *item {
+        TraitItem::Method(ref item) => attributes(allocator, &item.attrs).append(function(
+            allocator,
+            &item.attrs,
+            &item.sig,
+            item.default.as_ref(),
+        )),
+        TraitItem::Const(ref item) => {
+            let mut doc = attributes(allocator, &item.attrs)
+                .append(allocator.text("const "))
+                .append(allocator.as_string(&item.ident))
+                .append(allocator.text(": "))
+                .append(ty(allocator, &item.ty));
+            if let Some((_, ref default)) = item.default {
+                doc = doc
+                    .append(allocator.text(" = "))
+                    .append(expr(allocator, default));
+            }
+            doc.append(allocator.text(";"))
+        }
+        TraitItem::Type(ref item) => {
+            let mut doc = attributes(allocator, &item.attrs)
+                .append(allocator.text("type "))
+                .append(allocator.as_string(&item.ident))
+                .append(generics(allocator, &item.generics));
+            if !item.bounds.is_empty() {
+                doc = doc
+                    .append(allocator.text(": "))
+                    .append(bounds(allocator, &item.bounds));
+            }
+            if let Some((_, ref default)) = item.default {
+                doc = doc
+                    .append(allocator.text(" = "))
+                    .append(ty(allocator, default));
+            }
+            doc.append(allocator.text(";"))
+        }
+        _ => tokens(allocator, item),
+    }
+}
+
+/// Formats a function signature followed by its body, or by `;` if it has none. The body starts
+/// with the inner attributes among `attrs`.
+fn function<'a, D, A>(
+    allocator: &'a D,
+    attrs: &[Attribute],
+    sig: &Signature,
+    body: Option<&Block>,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = allocator.nil();
+    if sig.constness.is_some() {
+        doc = doc.append(allocator.text("const "));
+    }
+    if sig.asyncness.is_some() {
+        doc = doc.append(allocator.text("async "));
+    }
+    if sig.unsafety.is_some() {
+        doc = doc.append(allocator.text("unsafe "));
+    }
+    if let Some(ref abi) = sig.abi {
+        doc = doc.append(allocator.text("extern "));
+        if let Some(ref name) = abi.name {
+            doc = doc
+                .append(tokens(allocator, name))
+                .append(allocator.text(" "));
+        }
+    }
+    let inputs = sig.inputs.iter().map(|input| match *input {
+        FnArg::Receiver(ref receiver) => {
+            let mut doc = allocator.nil();
+            if let Some((_, ref lifetime)) = receiver.reference {
+                doc = doc.append(allocator.text("&"));
+                if let Some(ref lifetime) = *lifetime {
+                    doc = doc
+                        .append(allocator.as_string(lifetime))
+                        .append(allocator.text(" "));
+                }
+            }
+            if receiver.mutability.is_some() {
+                doc = doc.append(allocator.text("mut "));
+            }
+            doc.append(allocator.text("self"))
+        }
+        FnArg::Typed(ref arg) => pat(allocator, &arg.pat)
+            .append(allocator.text(": "))
+            .append(ty(allocator, &arg.ty)),
+    });
+    doc = doc
+        .append(allocator.text("fn "))
+        .append(allocator.as_string(&sig.ident))
+        .append(generics(allocator, &sig.generics))
+        .append(value::delimited(allocator, "(", inputs, ")"));
+    if let ReturnType::Type(_, ref output) = sig.output {
+        doc = doc
+            .append(allocator.text(" -> "))
+            .append(ty(allocator, output));
+    }
+    match body {
+        Some(body) => {
+            let inner = inner_attributes(allocator, attrs);
+            let empty = inner.is_none() && body.stmts.is_empty();
+            let stmts = body.stmts.iter().map(|s| stmt(allocator, s));
+            doc.append(where_clause_open(allocator, &sig.generics))
+                .append(braces(allocator, inner.into_iter().chain(stmts), empty))
+        }
+        None => doc
+            .append(where_clause(allocator, &sig.generics, false))
+            .append(allocator.text(";")),
+    }
+}
+
+/// Formats the fields of a tuple struct or variant, or of a variant with named fields.
+fn struct_fields<'a, D, A>(allocator: &'a D, fields: &Fields) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *fields {
+        Fields::Named(ref fields) => allocator.text(" ").append(value::block(
+            allocator,
+            "{",
+            fields
+                .named
+                .iter()
+                .map(|field| struct_field(allocator, field)),
+            "}",
+            INDENT,
+            true,
+            true,
+        )),
+        Fields::Unnamed(ref fields) => value::delimited(
+            allocator,
+            "(",
+            fields
+                .unnamed
+                .iter()
+                .map(|field| struct_field(allocator, field)),
+            ")",
+        ),
+        Fields::Unit => allocator.nil(),
+    }
+}
+
+fn struct_field<'a, D, A>(allocator: &'a D, field: &syn::Field) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = attributes(allocator, &field.attrs).append(visibility(allocator, &field.vis));
+    if let Some(ref ident) = field.ident {
+        doc = doc
+            .append(allocator.as_string(ident))
+            .append(allocator.text(": "));
+    }
+    doc.append(ty(allocator, &field.ty))
+}
+
+fn block<'a, D, A>(allocator: &'a D, block: &Block) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    braces(
+        allocator,
+        block.stmts.iter().map(|s| stmt(allocator, s)),
+        block.stmts.is_empty(),
+    )
+}
+
+/// Formats a block consisting of a single expression so that it can be put on one line, like
+/// `{ x }`, if the enclosing group fits.
+fn inline_block<'a, D, A>(allocator: &'a D, block: &Block) -> Option<DocBuilder<'a, D, A>>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let e = match block.stmts[..] {
+        [Stmt::Expr(ref e)] => e,
+        _ => return None,
+    };
+    match *e {
+        Expr::Block(_)
+        | Expr::ForLoop(_)
+        | Expr::If(_)
+        | Expr::Loop(_)
+        | Expr::Match(_)
+        | Expr::Unsafe(_)
+        | Expr::While(_) => None,
+        _ => Some(
+            allocator
+                .text("{")
+                .append(allocator.space().append(expr(allocator, e)).nest(INDENT))
+                .append(allocator.space())
+                .append(allocator.text("}")),
+        ),
+    }
+}
+
+/// Formats an `if` expression. Chains with an `else` where each branch is a single expression are
+/// put on one line if they fit.
+fn if_else<'a, D, A>(allocator: &'a D, e: &syn::ExprIf) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut branches = vec![(Some(&*e.cond), &e.then_branch)];
+    let mut current = e;
+    let mut otherwise = None;
+    while let Some((_, ref else_branch)) = current.else_branch {
+        match **else_branch {
+            Expr::If(ref e) => {
+                branches.push((Some(&*e.cond), &e.then_branch));
+                current = e;
+            }
+            Expr::Block(ref e) if e.label.is_none() => {
+                branches.push((None, &e.block));
+                break;
+            }
+            ref e => {
+                otherwise = Some(e);
+                break;
+            }
+        }
+    }
+
+    let complete = otherwise.is_none() && branches.last().map_or(false, |b| b.0.is_none());
+    let inline = if complete {
+        branches
+            .iter()
+            .map(|&(_, b)| inline_block(allocator, b))
+            .collect::<Option<Vec<_>>>()
+    } else {
+        None
+    };
+    let blocks = match inline {
+        Some(blocks) => blocks,
+        None => branches.iter().map(|&(_, b)| block(allocator, b)).collect(),
+    };
+
+    let mut doc = allocator.nil();
+    for (i, (&(cond, _), body)) in branches.iter().zip(blocks).enumerate() {
+        if i != 0 {
+            doc = doc.append(allocator.text(" else "));
+        }
+        if let Some(cond) = cond {
+            doc = doc
+                .append(allocator.text("if "))
+                .append(expr(allocator, cond))
+                .append(allocator.text(" "));
+        }
+        doc = doc.append(body);
+    }
+    if let Some(otherwise) = otherwise {
+        doc = doc
+            .append(allocator.text(" else "))
+            .append(expr(allocator, otherwise));
+    }
+    doc.group()
+}
+
+/// Places each of `docs` on its own line between braces, or returns `{}` if `empty`.
+fn braces<'a, D, A, I>(allocator: &'a D, docs: I, empty: bool) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = DocBuilder<'a, D, A>>,
+{
+    if empty {
+        return allocator.text("{}");
+    }
+    let body = docs.into_iter().fold(allocator.nil(), |body, doc| {
+        body.append(allocator.newline()).append(doc)
+    });
+    allocator
+        .text("{")
+        .append(body.nest(INDENT))
+        .append(allocator.newline())
+        .append(allocator.text("}"))
+}
+
+/// Separates `docs` by blank lines if `blank` is set. Used with `braces`, which already puts each
+/// document on its own line.
+fn separated<'a, D, A, I>(
+    allocator: &'a D,
+    docs: I,
+    blank: bool,
+) -> impl Iterator<Item = DocBuilder<'a, D, A>>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = DocBuilder<'a, D, A>>,
+{
+    let mut docs = docs.into_iter().peekable();
+    std::iter::from_fn(move || {
+        let doc = docs.next()?;
+        // The newline is written as text so that the empty line is not indented
+        Some(if blank && docs.peek().is_some() {
+            doc.append(allocator.text("\n"))
+        } else {
+            doc
+        })
+    })
+}
+
+/// An empty line which, unlike two newlines, is not indented.
+fn blank_line<'a, D, A>(allocator: &'a D) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    allocator.text("\n").append(allocator.newline())
+}
+
+fn arguments<'a, D, A>(allocator: &'a D, args: &Punctuated<Expr, Comma>) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    value::delimited(allocator, "(", args.iter().map(|e| expr(allocator, e)), ")")
+}
+
+fn tuple<'a, D, A, I>(allocator: &'a D, docs: I) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: ExactSizeIterator<Item = DocBuilder<'a, D, A>>,
+{
+    if docs.len() == 1 {
+        allocator
+            .text("(")
+            .append(allocator.concat(docs))
+            .append(allocator.text(",)"))
+    } else {
+        value::delimited(allocator, "(", docs, ")")
+    }
+}
+
+/// Concatenates `docs` with `separator` between each of them.
+fn join<'a, D, A, I>(allocator: &'a D, docs: I, separator: &'static str) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = DocBuilder<'a, D, A>>,
+{
+    let mut doc = allocator.nil();
+    for (i, d) in docs.into_iter().enumerate() {
+        if i != 0 {
+            doc = doc.append(allocator.text(separator));
+        }
+        doc = doc.append(d);
+    }
+    doc
+}
+
+/// Formats a chain of method calls, field accesses, `?` and `.await`.
+///
+/// Chains with at least two method calls are broken before each `.` if they do not fit on one
+/// line. Field accesses before the first call stay attached to the receiver.
+fn chain<'a, D, A>(allocator: &'a D, mut current: &Expr) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    // The links of the chain, starting from the outermost
+    let mut links = Vec::new();
+    loop {
+        match *current {
+            Expr::MethodCall(ref e) => {
+                let mut doc = allocator.text(".").append(allocator.as_string(&e.method));
+                if let Some(ref turbofish) = e.turbofish {
+                    let args = turbofish.args.iter().map(|arg| match *arg {
+                        syn::GenericMethodArgument::Type(ref t) => ty(allocator, t),
+                        syn::GenericMethodArgument::Const(ref e) => expr(allocator, e),
+                    });
+                    doc = doc
+                        .append(allocator.text("::<"))
+                        .append(join(allocator, args, ", "))
+                        .append(allocator.text(">"));
+                }
+                links.push((Link::Call, doc.append(arguments(allocator, &e.args))));
+                current = &e.receiver;
+            }
+            Expr::Field(ref e) => {
+                let doc = allocator.text(".").append(member(allocator, &e.member));
+                links.push((Link::Field, doc));
+                current = &e.base;
+            }
+            Expr::Await(ref e) => {
+                links.push((Link::Field, allocator.text(".await")));
+                current = &e.base;
+            }
+            Expr::Try(ref e) => {
+                links.push((Link::Try, allocator.text("?")));
+                current = &e.expr;
+            }
+            _ => break,
+        }
+    }
+
+    let mut head = expr(allocator, current);
+    let calls = links
+        .iter()
+        .filter(|&&(link, _)| link == Link::Call)
+        .count();
+    if calls < 2 {
+        return head.append(allocator.concat(links.into_iter().rev().map(|(_, doc)| doc)));
+    }
+
+    let mut tail = allocator.nil();
+    let mut seen_call = false;
+    for (link, doc) in links.into_iter().rev() {
+        seen_call |= link == Link::Call;
+        if !seen_call {
+            head = head.append(doc);
+        } else if link == Link::Try {
+            tail = tail.append(doc);
+        } else {
+            tail = tail
+                .append(allocator.nil().if_break(allocator.space()))
+                .append(doc);
+        }
+    }
+    head.append(tail.nest(INDENT)).group()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Link {
+    Call,
+    /// A field access or `.await`.
+    Field,
+    Try,
+}
+
+/// Formats a sequence of binary operators, breaking before each operator if they do not fit.
+fn binary<'a, D, A>(allocator: &'a D, mut current: &Expr) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut operands = Vec::new();
+    while let Expr::Binary(ref e) = *current {
+        operands.push((&e.op, &e.right));
+        current = &e.left;
+    }
+    let rest = operands.into_iter().rev().map(|(op, right)| {
+        allocator
+            .space()
+            .append(binary_op(allocator, op))
+            .append(allocator.text(" "))
+            .append(expr(allocator, right))
+    });
+    expr(allocator, current)
+        .append(allocator.concat(rest).nest(INDENT))
+        .group()
+}
+
+fn binary_op<'a, D, A>(allocator: &'a D, op: &BinOp) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    allocator.text(op.to_token_stream().to_string())
+}
+
+/// Formats a macro call. The arguments are laid out like those of a function call if they parse
+/// as comma separated expressions, and printed from their tokens otherwise.
+fn macro_call<'a, D, A>(allocator: &'a D, mac: &Macro) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let name = path(allocator, &mac.path).append(allocator.text("!"));
+    let (open, close) = match mac.delimiter {
+        MacroDelimiter::Paren(_) => ("(", ")"),
+        MacroDelimiter::Bracket(_) => ("[", "]"),
+        MacroDelimiter::Brace(_) => {
+            return name
+                .append(allocator.text(" { "))
+                .append(tokens(allocator, &mac.tokens))
+                .append(allocator.text(" }"))
+        }
+    };
+    match mac.parse_body_with(Punctuated::<Expr, Comma>::parse_terminated) {
+        Ok(args) => name.append(value::delimited(
+            allocator,
+            open,
+            args.iter().map(|e| expr(allocator, e)),
+            close,
+        )),
+        Err(_) => name
+            .append(allocator.text(open))
+            .append(tokens(allocator, &mac.tokens))
+            .append(allocator.text(close)),
+    }
+}
+
+fn path<'a, D, A>(allocator: &'a D, path: &Path) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = allocator.nil();
+    if path.leading_colon.is_some() {
+        doc = doc.append(allocator.text("::"));
+    }
+    for (i, segment) in path.segments.iter().enumerate() {
+        if i != 0 {
+            doc = doc.append(allocator.text("::"));
+        }
+        doc = doc.append(allocator.as_string(&segment.ident));
+        match segment.arguments {
+            PathArguments::None => {}
+            PathArguments::AngleBracketed(ref arguments) => {
+                let args = arguments.args.iter().map(|arg| match *arg {
+                    GenericArgument::Lifetime(ref lifetime) => allocator.as_string(lifetime),
+                    GenericArgument::Type(ref t) => ty(allocator, t),
+                    GenericArgument::Binding(ref binding) => allocator
+                        .as_string(&binding.ident)
+                        .append(allocator.text(" = "))
+                        .append(ty(allocator, &binding.ty)),
+                    GenericArgument::Constraint(ref constraint) => allocator
+                        .as_string(&constraint.ident)
+                        .append(allocator.text(": "))
+                        .append(bounds(allocator, &constraint.bounds)),
+                    GenericArgument::Const(ref e) => expr(allocator, e),
+                });
+                doc = doc
+                    .append(allocator.text(if arguments.colon2_token.is_some() {
+                        "::<"
+                    } else {
+                        "<"
+                    }))
+                    .append(join(allocator, args, ", "))
+                    .append(allocator.text(">"));
+            }
+            PathArguments::Parenthesized(ref args) => {
+                doc = doc
+                    .append(allocator.text("("))
+                    .append(join(
+                        allocator,
+                        args.inputs.iter().map(|t| ty(allocator, t)),
+                        ", ",
+                    ))
+                    .append(allocator.text(")"));
+                if let ReturnType::Type(_, ref output) = args.output {
+                    doc = doc
+                        .append(allocator.text(" -> "))
+                        .append(ty(allocator, output));
+                }
+            }
+        }
+    }
+    doc
+}
+
+fn member<'a, D, A>(allocator: &'a D, member: &Member) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *member {
+        Member::Named(ref ident) => allocator.as_string(ident),
+        Member::Unnamed(ref index) => allocator.as_string(index.index),
+    }
+}
+
+fn label<'a, D, A>(allocator: &'a D, label: &Option<syn::Label>) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *label {
+        Some(ref label) => allocator
+            .as_string(&label.name)
+            .append(allocator.text(": ")),
+        None => allocator.nil(),
+    }
+}
+
+fn visibility<'a, D, A>(allocator: &'a D, vis: &Visibility) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *vis {
+        Visibility::Public(_) => allocator.text("pub "),
+        Visibility::Crate(_) => allocator.text("crate "),
+        Visibility::Restricted(ref vis) => allocator
+            .text(if vis.in_token.is_some() {
+                "pub(in "
+            } else {
+                "pub("
+            })
+            .append(path(allocator, &vis.path))
+            .append(allocator.text(") ")),
+        Visibility::Inherited => allocator.nil(),
+    }
+}
+
+fn generics<'a, D, A>(allocator: &'a D, generics: &Generics) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    if generics.params.is_empty() {
+        return allocator.nil();
+    }
+    let params = generics.params.iter().map(|param| match *param {
+        GenericParam::Lifetime(ref param) => {
+            let mut doc = allocator.as_string(&param.lifetime);
+            if !param.bounds.is_empty() {
+                doc = doc.append(allocator.text(": ")).append(join(
+                    allocator,
+                    param.bounds.iter().map(|bound| allocator.as_string(bound)),
+                    " + ",
+                ));
+            }
+            doc
+        }
+        GenericParam::Type(ref param) => {
+            let mut doc = allocator.as_string(&param.ident);
+            if !param.bounds.is_empty() {
+                doc = doc
+                    .append(allocator.text(": "))
+                    .append(bounds(allocator, &param.bounds));
+            }
+            if let Some(ref default) = param.default {
+                doc = doc
+                    .append(allocator.text(" = "))
+                    .append(ty(allocator, default));
+            }
+            doc
+        }
+        GenericParam::Const(ref param) => allocator
+            .text("const ")
+            .append(allocator.as_string(&param.ident))
+            .append(allocator.text(": "))
+            .append(ty(allocator, &param.ty)),
+    });
+    allocator
+        .text("<")
+        .append(join(allocator, params, ", "))
+        .append(allocator.text(">"))
+}
+
+fn bounds<'a, D, A, P>(
+    allocator: &'a D,
+    bounds: &Punctuated<TypeParamBound, P>,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let bounds = bounds.iter().map(|bound| match *bound {
+        TypeParamBound::Trait(ref bound) => {
+            let mut doc = allocator.nil();
+            if let Some(ref lifetimes) = bound.lifetimes {
+                doc = doc
+                    .append(tokens(allocator, lifetimes))
+                    .append(allocator.text(" "));
+            }
+            if let syn::TraitBoundModifier::Maybe(_) = bound.modifier {
+                doc = doc.append(allocator.text("?"));
+            }
+            doc = doc.append(path(allocator, &bound.path));
+            if bound.paren_token.is_some() {
+                allocator.text("(").append(doc).append(allocator.text(")"))
+            } else {
+                doc
+            }
+        }
+        TypeParamBound::Lifetime(ref lifetime) => allocator.as_string(lifetime),
+    });
+    join(allocator, bounds, " + ")
+}
+
+/// Formats the where clause of `generics`. The last predicate is followed by a comma if
+/// `trailing_comma` is set, which is left out before a `;`.
+fn where_clause<'a, D, A>(
+    allocator: &'a D,
+    generics: &Generics,
+    trailing_comma: bool,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let clause = match generics.where_clause {
+        Some(ref clause) if !clause.predicates.is_empty() => clause,
+        _ => return allocator.nil(),
+    };
+    let last = clause.predicates.len() - 1;
+    let predicates = clause.predicates.iter().enumerate().map(|(i, predicate)| {
+        let doc = match *predicate {
+            WherePredicate::Type(ref predicate) => {
+                let mut doc = allocator.nil();
+                if let Some(ref lifetimes) = predicate.lifetimes {
+                    doc = doc
+                        .append(tokens(allocator, lifetimes))
+                        .append(allocator.text(" "));
+                }
+                doc.append(ty(allocator, &predicate.bounded_ty))
+                    .append(allocator.text(": "))
+                    .append(bounds(allocator, &predicate.bounds))
+            }
+            WherePredicate::Lifetime(ref predicate) => allocator
+                .as_string(&predicate.lifetime)
+                .append(allocator.text(": "))
+                .append(join(
+                    allocator,
+                    predicate
+                        .bounds
+                        .iter()
+                        .map(|bound| allocator.as_string(bound)),
+                    " + ",
+                )),
+            WherePredicate::Eq(ref predicate) => tokens(allocator, predicate),
+        };
+        let doc = allocator.newline().append(doc);
+        if i != last || trailing_comma {
+            doc.append(allocator.text(","))
+        } else {
+            doc
+        }
+    });
+    allocator
+        .newline()
+        .append(allocator.text("where"))
+        .append(allocator.concat(predicates).nest(INDENT))
+}
+
+/// Formats the where clause of `generics` followed by what precedes an opening brace.
+fn where_clause_open<'a, D, A>(allocator: &'a D, generics: &Generics) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match generics.where_clause {
+        Some(ref clause) if !clause.predicates.is_empty() => {
+            where_clause(allocator, generics, true).append(allocator.newline())
+        }
+        _ => allocator.text(" "),
+    }
+}
+
+fn use_tree<'a, D, A>(allocator: &'a D, tree: &UseTree) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *tree {
+        UseTree::Path(ref tree) => allocator
+            .as_string(&tree.ident)
+            .append(allocator.text("::"))
+            .append(use_tree(allocator, &tree.tree)),
+        UseTree::Name(ref tree) => allocator.as_string(&tree.ident),
+        UseTree::Rename(ref tree) => allocator
+            .as_string(&tree.ident)
+            .append(allocator.text(" as "))
+            .append(allocator.as_string(&tree.rename)),
+        UseTree::Glob(_) => allocator.text("*"),
+        UseTree::Group(ref tree) => value::delimited(
+            allocator,
+            "{",
+            tree.items.iter().map(|tree| use_tree(allocator, tree)),
+            "}",
+        ),
+    }
+}
+
+/// Formats outer attributes, each on its own line.
+fn attributes<'a, D, A>(allocator: &'a D, attrs: &[Attribute]) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    allocator.concat(
+        attrs
+            .iter()
+            .filter(|attr| is_outer(attr))
+            .map(|attr| attribute(allocator, attr).append(allocator.newline())),
+    )
+}
+
+/// Formats inner attributes, each on its own line, or returns `None` if there are none.
+fn inner_attributes<'a, D, A>(allocator: &'a D, attrs: &[Attribute]) -> Option<DocBuilder<'a, D, A>>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut inner = attrs.iter().filter(|attr| !is_outer(attr));
+    let first = attribute(allocator, inner.next()?);
+    Some(inner.fold(first, |doc, attr| {
+        doc.append(allocator.newline())
+            .append(attribute(allocator, attr))
+    }))
+}
+
+fn attribute<'a, D, A>(allocator: &'a D, attr: &Attribute) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let inner = !is_outer(attr);
+    let meta = match attr.parse_meta() {
+        Ok(meta) => meta,
+        Err(_) => {
+            // The path is spaced together with the tokens, which may start with `=` or `(`
+            let mut stream = attr.path.to_token_stream();
+            stream.extend(attr.tokens.clone());
+            return allocator
+                .text(if inner { "#![" } else { "#[" })
+                .append(tokens(allocator, &stream))
+                .append(allocator.text("]"));
+        }
+    };
+    if let Meta::NameValue(ref meta) = meta {
+        if let syn::Lit::Str(ref doc) = meta.lit {
+            let doc = doc.value();
+            // A line starting with `/` would turn `///` into a plain comment
+            let sugared = !doc.contains('\r') && doc.lines().all(|line| !line.starts_with('/'));
+            if meta.path.is_ident("doc") && sugared {
+                // Each line of a block comment becomes a line comment
+                let marker = if inner { "//!" } else { "///" };
+                let mut lines = allocator.nil();
+                for (i, line) in doc.split('\n').enumerate() {
+                    if i != 0 {
+                        lines = lines.append(allocator.newline());
+                    }
+                    lines = lines.append(allocator.text(format!("{}{}", marker, line)));
+                }
+                return lines;
+            }
+        }
+    }
+    allocator
+        .text(if inner { "#![" } else { "#[" })
+        .append(meta_doc(allocator, &meta))
+        .append(allocator.text("]"))
+}
+
+fn is_outer(attr: &Attribute) -> bool {
+    match attr.style {
+        syn::AttrStyle::Outer => true,
+        syn::AttrStyle::Inner(_) => false,
+    }
+}
+
+fn meta_doc<'a, D, A>(allocator: &'a D, meta: &Meta) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    match *meta {
+        Meta::Path(ref p) => path(allocator, p),
+        Meta::List(ref list) => {
+            let nested = list.nested.iter().map(|nested| match *nested {
+                NestedMeta::Meta(ref meta) => meta_doc(allocator, meta),
+                NestedMeta::Lit(ref lit) => tokens(allocator, lit),
+            });
+            path(allocator, &list.path)
+                .append(allocator.text("("))
+                .append(join(allocator, nested, ", "))
+                .append(allocator.text(")"))
+        }
+        Meta::NameValue(ref meta) => path(allocator, &meta.path)
+            .append(allocator.text(" = "))
+            .append(tokens(allocator, &meta.lit)),
+    }
+}
+
+/// Prints `node` from its tokens, spaced roughly like `rustfmt` would.
+fn tokens<'a, D, A, T>(allocator: &'a D, node: &T) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: ?Sized + ToTokens,
+{
+    let mut text = String::new();
+    write_tokens(node.to_token_stream(), &mut text);
+    allocator.text(text)
+}
+
+/// A token, with operators made of several characters merged into one.
+enum Token {
+    Word(String),
+    Op(String),
+    /// `<` or one or more `>` around generic arguments, like in `Vec<u8>` or `collect::<T>()`.
+    Angle(String),
+    Group(Delimiter, TokenStream),
+}
+
+/// The operators made of several characters. Every prefix of an operator is an operator itself.
+const OPERATORS: &[&str] = &[
+    "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "%=", "^=", "&=",
+    "|=", "<<", ">>", "<<=", ">>=", "..", "...", "..=",
+];
+
+fn write_tokens(stream: TokenStream, out: &mut String) {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut joint = false;
+    for tree in stream {
+        match tree {
+            TokenTree::Punct(punct) => {
+                let c = punct.as_char();
+                let token = match tokens.last_mut() {
+                    Some(&mut Token::Op(ref mut op))
+                        if joint && OPERATORS.contains(&&format!("{}{}", op, c)[..]) =>
+                    {
+                        op.push(c);
+                        None
+                    }
+                    _ => Some(Token::Op(c.to_string())),
+                };
+                tokens.extend(token);
+                joint = punct.spacing() == Spacing::Joint;
+                continue;
+            }
+            TokenTree::Ident(ident) => tokens.push(Token::Word(ident.to_string())),
+            TokenTree::Literal(literal) => tokens.push(Token::Word(literal.to_string())),
+            TokenTree::Group(group) => tokens.push(Token::Group(group.delimiter(), group.stream())),
+        }
+        joint = false;
+    }
+
+    // Only a `<` after a type name or `::` opens generic arguments, any other one is a comparison
+    let mut depth = 0;
+    let mut after_type = false;
+    let tokens = tokens
+        .into_iter()
+        .map(|token| {
+            let token = match token {
+                Token::Op(op) => {
+                    if op == "<" && after_type {
+                        depth += 1;
+                        Token::Angle(op)
+                    } else if op.chars().all(|c| c == '>') && op.len() <= depth {
+                        depth -= op.len();
+                        Token::Angle(op)
+                    } else {
+                        Token::Op(op)
+                    }
+                }
+                token => token,
+            };
+            after_type = match token {
+                Token::Word(ref word) => word.starts_with(char::is_uppercase),
+                Token::Op(ref op) => op == "::",
+                _ => false,
+            };
+            token
+        })
+        .collect::<Vec<_>>();
+
+    let mut previous: Option<&Token> = None;
+    for token in &tokens {
+        if let Some(previous) = previous {
+            if space_between(previous, token) {
+                out.push(' ');
+            }
+        }
+        match *token {
+            Token::Word(ref word) => out.push_str(word),
+            Token::Op(ref op) | Token::Angle(ref op) => out.push_str(op),
+            Token::Group(delimiter, ref stream) => {
+                let (open, close) = match delimiter {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{ ", " }"),
+                    Delimiter::None => ("", ""),
+                };
+                if delimiter == Delimiter::Brace && stream.is_empty() {
+                    out.push_str("{}");
+                } else {
+                    out.push_str(open);
+                    write_tokens(stream.clone(), out);
+                    out.push_str(close);
+                }
+            }
+        }
+        previous = Some(token);
+    }
+}
+
+fn space_between(previous: &Token, next: &Token) -> bool {
+    match *next {
+        Token::Op(ref op) if [",", ";", ".", "?", ":", "::", ".."].contains(&&op[..]) => {
+            return false
+        }
+        Token::Op(ref op) if op == "!" => {
+            if let Token::Word(_) = *previous {
+                return false;
+            }
+        }
+        Token::Angle(_) => return false,
+        Token::Group(Delimiter::Parenthesis, _) | Token::Group(Delimiter::Bracket, _) => {
+            match *previous {
+                Token::Word(_) | Token::Group(..) | Token::Angle(_) => return false,
+                Token::Op(ref op) if op == "!" || op == "#" => return false,
+                Token::Op(_) => {}
+            }
+        }
+        _ => {}
+    }
+    match *previous {
+        Token::Op(ref op) => ![".", "::", "#", "&", "'", "..", "..=", "!", "$"].contains(&&op[..]),
+        Token::Angle(ref op) => op != "<",
+        _ => true,
+    }
+}
@@ -0,0 +1,494 @@
+//! A `serde::Serializer` which turns any `T: Serialize` into a document.
+//!
+//! Values are laid out like Rust's `Debug` output, using the layouts of the
+//! [`value`](../value/index.html) module: structs as `Name { field: value }`, sequences as
+//! `[a, b]`, maps as `{key: value}` and enum variants by their name.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate serde;
+//! # extern crate pretty;
+//! use pretty::{ser, BoxAllocator, BoxDoc, Doc};
+//!
+//! #[derive(Serialize)]
+//! enum Shape {
+//!     Circle { radius: f64 },
+//!     Polygon(Vec<(i32, i32)>),
+//! }
+//!
+//! # fn main() {
+//! let shapes = vec![Shape::Circle { radius: 1.5 }, Shape::Polygon(vec![(0, 0), (1, 1)])];
+//! let doc: Doc<BoxDoc<()>> = ser::to_doc(&BoxAllocator, &shapes).unwrap().into();
+//!
+//! assert_eq!(
+//!     doc.pretty(80).to_string(),
+//!     "[Circle { radius: 1.5 }, Polygon([(0, 0), (1, 1)])]"
+//! );
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::ser::{self, Serialize};
+
+use value;
+use {DocAllocator, DocBuilder};
+
+/// Serializes `value` into a document.
+pub fn to_doc<'a, D, A, T>(allocator: &'a D, value: &T) -> Result<DocBuilder<'a, D, A>, Error>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: ?Sized + Serialize,
+{
+    value.serialize(Serializer::new(allocator))
+}
+
+/// An error raised by a `Serialize` implementation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+/// Serializes values into documents allocated by a `DocAllocator`.
+pub struct Serializer<'a, D, A = ()>
+where
+    D: ?Sized + 'a,
+{
+    allocator: &'a D,
+    _annotation: PhantomData<A>,
+}
+
+impl<'a, D, A> Clone for Serializer<'a, D, A>
+where
+    D: ?Sized,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, D, A> Copy for Serializer<'a, D, A> where D: ?Sized {}
+
+impl<'a, D, A> Serializer<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    pub fn new(allocator: &'a D) -> Serializer<'a, D, A> {
+        Serializer {
+            allocator,
+            _annotation: PhantomData,
+        }
+    }
+
+    fn compound(self, name: &'static str, kind: Kind) -> Compound<'a, D, A> {
+        Compound {
+            serializer: self,
+            name,
+            kind,
+            items: Vec::new(),
+            fields: Vec::new(),
+            entries: Vec::new(),
+            key: None,
+        }
+    }
+}
+
+impl<'a, D, A> ser::Serializer for Serializer<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a, D, A>;
+    type SerializeTuple = Compound<'a, D, A>;
+    type SerializeTupleStruct = Compound<'a, D, A>;
+    type SerializeTupleVariant = Compound<'a, D, A>;
+    type SerializeMap = Compound<'a, D, A>;
+    type SerializeStruct = Compound<'a, D, A>;
+    type SerializeStructVariant = Compound<'a, D, A>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.as_string(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(format!("{:?}", v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(format!("{:?}", v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(format!("{:?}", v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(format!("{:?}", v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        let docs = v.iter().map(|byte| self.allocator.as_string(byte));
+        Ok(value::delimited(self.allocator, "[", docs, "]"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text("None"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let doc = value.serialize(self)?;
+        Ok(value::tuple(self.allocator, "Some", Some(doc)))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text("()"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(name))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(self.allocator.text(variant))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let doc = value.serialize(self)?;
+        Ok(value::tuple(self.allocator, name, Some(doc)))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let doc = value.serialize(self)?;
+        Ok(value::tuple(self.allocator, variant, Some(doc)))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(self.compound("", Kind::Seq))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(self.compound("", Kind::Tuple))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Ok(self.compound(name, Kind::Tuple))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(self.compound(variant, Kind::Tuple))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(self.compound("", Kind::Map))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Ok(self.compound(name, Kind::Struct))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(self.compound(variant, Kind::Struct))
+    }
+}
+
+enum Kind {
+    Seq,
+    Tuple,
+    Map,
+    Struct,
+}
+
+/// Collects the elements of sequences, tuples, maps and structs.
+pub struct Compound<'a, D, A = ()>
+where
+    D: ?Sized + DocAllocator<'a, A> + 'a,
+{
+    serializer: Serializer<'a, D, A>,
+    name: &'static str,
+    kind: Kind,
+    items: Vec<DocBuilder<'a, D, A>>,
+    fields: Vec<(&'static str, DocBuilder<'a, D, A>)>,
+    entries: Vec<(DocBuilder<'a, D, A>, DocBuilder<'a, D, A>)>,
+    key: Option<DocBuilder<'a, D, A>>,
+}
+
+impl<'a, D, A> Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    fn item<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let doc = value.serialize(self.serializer)?;
+        self.items.push(doc);
+        Ok(())
+    }
+
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let doc = value.serialize(self.serializer)?;
+        self.fields.push((key, doc));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<DocBuilder<'a, D, A>, Error> {
+        let allocator = self.serializer.allocator;
+        Ok(match self.kind {
+            Kind::Seq => value::delimited(allocator, "[", self.items, "]"),
+            // Like Rust, a tuple with one element needs a trailing comma but a tuple struct does not
+            Kind::Tuple if self.name.is_empty() && self.items.len() == 1 => allocator
+                .text("(")
+                .append(self.items.into_iter().next().unwrap())
+                .append(allocator.text(",)")),
+            Kind::Tuple => value::tuple(allocator, self.name, self.items),
+            Kind::Map => value::map(allocator, self.entries),
+            Kind::Struct => value::record(allocator, self.name, self.fields),
+        })
+    }
+}
+
+impl<'a, D, A> ser::SerializeSeq for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeTuple for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeTupleStruct for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeTupleVariant for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeMap for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(key.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("serialize_value called before serialize_key".into()))?;
+        let value = value.serialize(self.serializer)?;
+        self.entries.push((key, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeStruct for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl<'a, D, A> ser::SerializeStructVariant for Compound<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    type Ok = DocBuilder<'a, D, A>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
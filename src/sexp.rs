@@ -0,0 +1,375 @@
+//! Parsing and pretty printing of s-expressions.
+//!
+//! Lists are laid out on a single line when they fit. Otherwise the arguments of a call are
+//! aligned under its first argument, while the body of a special form such as `defun` or `let` is
+//! indented by 2 spaces and its distinguished arguments are kept on the first line.
+//!
+//! ```
+//! use pretty::sexp::{self, Printer};
+//! use pretty::BoxAllocator;
+//!
+//! let exprs = sexp::parse("(defun sum (xs) (reduce #'+ xs :initial-value 0))").unwrap();
+//! let printer = Printer::<()>::new();
+//! let doc = printer.to_doc(&BoxAllocator, &exprs[0]).1;
+//!
+//! assert_eq!(
+//!     doc.pretty(30).to_string(),
+//!     "(defun sum (xs)
+//!   (reduce #'+
+//!           xs
+//!           :initial-value
+//!           0))"
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use {DocAllocator, DocBuilder};
+
+/// A symbolic expression.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SExp {
+    /// A symbol or number, stored as the text it is printed as.
+    Atom(String),
+    /// A string literal.
+    String(String),
+    /// A quoted expression, `'expr`.
+    Quote(Box<SExp>),
+    List(Vec<SExp>),
+}
+
+impl FromStr for SExp {
+    type Err = ParseError;
+
+    /// Parses a single expression.
+    fn from_str(input: &str) -> Result<SExp, ParseError> {
+        let mut parser = Parser { input, pos: 0 };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if parser.pos != input.len() {
+            return Err(parser.error("expected end of input"));
+        }
+        Ok(expr)
+    }
+}
+
+/// Parses a sequence of expressions. Comments starting with `;` are skipped.
+pub fn parse(input: &str) -> Result<Vec<SExp>, ParseError> {
+    let mut parser = Parser { input, pos: 0 };
+    let mut exprs = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.pos == input.len() {
+            return Ok(exprs);
+        }
+        exprs.push(parser.expr()?);
+    }
+}
+
+/// An error describing where the input of `parse` is malformed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    /// The byte offset of the error in the input.
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl error::Error for ParseError {}
+
+struct Parser<'s> {
+    input: &'s str,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while self.peek().map_or(false, |c| c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<SExp, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("expected an expression")),
+            Some(')') => Err(self.error("unexpected `)`")),
+            Some('(') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => return Err(self.error("unclosed list")),
+                        Some(')') => {
+                            self.bump();
+                            return Ok(SExp::List(items));
+                        }
+                        Some(_) => items.push(self.expr()?),
+                    }
+                }
+            }
+            Some('\'') => {
+                self.bump();
+                Ok(SExp::Quote(Box::new(self.expr()?)))
+            }
+            Some('"') => {
+                self.bump();
+                let mut s = String::new();
+                loop {
+                    match self.bump() {
+                        None => return Err(self.error("unclosed string")),
+                        Some('"') => return Ok(SExp::String(s)),
+                        Some('\\') => match self.bump() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(c) => s.push(c),
+                            None => return Err(self.error("unclosed string")),
+                        },
+                        Some(c) => s.push(c),
+                    }
+                }
+            }
+            Some(_) => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .map_or(false, |c| !c.is_whitespace() && !"()\";".contains(c))
+                {
+                    self.bump();
+                }
+                Ok(SExp::Atom(self.input[start..self.pos].to_string()))
+            }
+        }
+    }
+}
+
+/// The kinds of tokens which can be annotated.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Kind {
+    Symbol,
+    Number,
+    String,
+    /// The heads of special forms.
+    Special,
+}
+
+/// Converts s-expressions into documents.
+pub struct Printer<A = ()> {
+    indent: usize,
+    special: HashMap<String, usize>,
+    annotate: Option<Box<dyn Fn(Kind) -> A>>,
+}
+
+impl<A> Default for Printer<A> {
+    fn default() -> Printer<A> {
+        Printer::new()
+    }
+}
+
+impl<A> Printer<A> {
+    /// Creates a printer which indents bodies by 2 spaces, knows the special forms `defun`,
+    /// `defmacro`, `define`, `lambda`, `let`, `let*`, `letrec`, `when`, `unless`, `while`,
+    /// `dolist` and `progn`, and does not annotate anything.
+    pub fn new() -> Printer<A> {
+        let special = [
+            ("defun", 2),
+            ("defmacro", 2),
+            ("define", 1),
+            ("lambda", 1),
+            ("let", 1),
+            ("let*", 1),
+            ("letrec", 1),
+            ("when", 1),
+            ("unless", 1),
+            ("while", 1),
+            ("dolist", 1),
+            ("progn", 0),
+        ];
+        Printer {
+            indent: 2,
+            special: special
+                .iter()
+                .map(|&(name, args)| (name.to_string(), args))
+                .collect(),
+            annotate: None,
+        }
+    }
+
+    /// Sets the number of spaces the bodies of special forms are indented by.
+    pub fn indent(mut self, indent: usize) -> Printer<A> {
+        self.indent = indent;
+        self
+    }
+
+    /// Treats lists starting with the symbol `name` as a special form.
+    ///
+    /// The first `args` arguments are kept on the line of `name` and the remaining arguments form
+    /// the body, which is indented instead of aligned under the first argument.
+    pub fn special<S>(mut self, name: S, args: usize) -> Printer<A>
+    where
+        S: Into<String>,
+    {
+        self.special.insert(name.into(), args);
+        self
+    }
+
+    /// Annotates each token with the annotation returned by `annotate` for its kind.
+    pub fn annotate<F>(mut self, annotate: F) -> Printer<A>
+    where
+        F: Fn(Kind) -> A + 'static,
+    {
+        self.annotate = Some(Box::new(annotate));
+        self
+    }
+
+    pub fn to_doc<'a, D>(&self, allocator: &'a D, expr: &'a SExp) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match *expr {
+            SExp::Atom(ref atom) => {
+                let kind = if is_number(atom) {
+                    Kind::Number
+                } else {
+                    Kind::Symbol
+                };
+                self.token(kind, allocator.text(&atom[..]))
+            }
+            SExp::String(ref s) => self.token(Kind::String, allocator.text(quote(s))),
+            SExp::Quote(ref expr) => allocator.text("'").append(self.to_doc(allocator, expr)),
+            SExp::List(ref items) => self.list(allocator, items),
+        }
+    }
+
+    fn list<'a, D>(&self, allocator: &'a D, items: &'a [SExp]) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let (head, args) = match items.split_first() {
+            Some(split) => split,
+            None => return allocator.text("()"),
+        };
+
+        let special = match *head {
+            SExp::Atom(ref name) => self.special.get(name).map(|&args| (name, args)),
+            _ => None,
+        };
+        let body = match special {
+            // The distinguished arguments stay on the line of the head, the body is indented
+            Some((name, distinguished)) => {
+                let distinguished = distinguished.min(args.len());
+                let mut doc = self.token(Kind::Special, allocator.text(&name[..]));
+                for arg in &args[..distinguished] {
+                    doc = doc
+                        .append(allocator.text(" "))
+                        .append(self.to_doc(allocator, arg));
+                }
+                let body = args[distinguished..]
+                    .iter()
+                    .map(|arg| allocator.space().append(self.to_doc(allocator, arg)));
+                doc.append(allocator.concat(body).nest(self.indent))
+            }
+            // The arguments of a call are aligned under the first argument
+            None if !args.is_empty() && is_atom(head) => self
+                .to_doc(allocator, head)
+                .append(allocator.text(" "))
+                .append(self.spaced(allocator, args).align()),
+            None => self.spaced(allocator, items).align(),
+        };
+
+        allocator
+            .text("(")
+            .append(body)
+            .append(allocator.text(")"))
+            .group()
+            .align()
+    }
+
+    /// Lays out `exprs` separated by spaces.
+    fn spaced<'a, D>(&self, allocator: &'a D, exprs: &'a [SExp]) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let mut doc = allocator.nil();
+        for (i, expr) in exprs.iter().enumerate() {
+            if i != 0 {
+                doc = doc.append(allocator.space());
+            }
+            doc = doc.append(self.to_doc(allocator, expr));
+        }
+        doc
+    }
+
+    fn token<'a, D>(&self, kind: Kind, doc: DocBuilder<'a, D, A>) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match self.annotate {
+            Some(ref annotate) => doc.annotate(annotate(kind)),
+            None => doc,
+        }
+    }
+}
+
+/// Writes `s` as a string literal, escaping only what `parse` reads escaped.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_atom(expr: &SExp) -> bool {
+    match *expr {
+        SExp::Atom(_) | SExp::String(_) => true,
+        SExp::Quote(_) | SExp::List(_) => false,
+    }
+}
+
+fn is_number(atom: &str) -> bool {
+    let digits = atom.trim_start_matches(['-', '+']);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+}
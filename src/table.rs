@@ -0,0 +1,195 @@
+//! Tables with cells aligned in columns.
+//!
+//! The width of each column is the widest flat width of its cells, and every cell is padded to
+//! the width of its column. When a line of the table does not fit, or a cell can not be laid out
+//! on a single line, the rows are stacked instead, with each cell after the first on its own
+//! line.
+//!
+//! ```
+//! use pretty::table::{Alignment, Table};
+//! use pretty::{BoxAllocator, DocAllocator};
+//!
+//! let a = &BoxAllocator;
+//! let doc = Table::<_, ()>::new()
+//!     .align(1, Alignment::Right)
+//!     .row(vec![a.text("let width"), a.text("="), a.text("80;")])
+//!     .row(vec![a.text("let x"), a.text("+="), a.text("offset + 1;")])
+//!     .to_doc(a)
+//!     .1;
+//!
+//! assert_eq!(
+//!     doc.pretty(80).to_string(),
+//!     "let width  = 80;
+//! let x     += offset + 1;"
+//! );
+//! assert_eq!(
+//!     doc.pretty(20).to_string(),
+//!     "let width
+//!   =
+//!   80;
+//! let x
+//!   +=
+//!   offset + 1;"
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::cmp;
+
+use render::flat_width;
+use {DocAllocator, DocBuilder};
+
+/// How a cell is placed in the width of its column.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+/// A table of documents, laid out by `to_doc`.
+pub struct Table<'a, D, A = ()>
+where
+    D: ?Sized + DocAllocator<'a, A> + 'a,
+{
+    rows: Vec<Vec<DocBuilder<'a, D, A>>>,
+    alignments: Vec<Alignment>,
+    separator: Cow<'a, str>,
+    indent: usize,
+}
+
+impl<'a, D, A> Default for Table<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    fn default() -> Table<'a, D, A> {
+        Table::new()
+    }
+}
+
+impl<'a, D, A> Table<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    /// Creates a table without rows which separates its columns by a space, aligns cells to the
+    /// left and indents stacked cells by 2 spaces.
+    pub fn new() -> Table<'a, D, A> {
+        Table {
+            rows: Vec::new(),
+            alignments: Vec::new(),
+            separator: Cow::Borrowed(" "),
+            indent: 2,
+        }
+    }
+
+    /// Adds a row. Rows may have fewer cells than others, the missing cells are left empty.
+    pub fn row(mut self, cells: Vec<DocBuilder<'a, D, A>>) -> Table<'a, D, A> {
+        self.rows.push(cells);
+        self
+    }
+
+    /// Sets the alignment of the cells in `column`.
+    pub fn align(mut self, column: usize, alignment: Alignment) -> Table<'a, D, A> {
+        if self.alignments.len() <= column {
+            self.alignments.resize(column + 1, Alignment::Left);
+        }
+        self.alignments[column] = alignment;
+        self
+    }
+
+    /// Sets the text put between the columns.
+    pub fn separator<S>(mut self, separator: S) -> Table<'a, D, A>
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Sets the number of spaces the cells after the first one of a row are indented by when
+    /// the rows are stacked.
+    pub fn indent(mut self, indent: usize) -> Table<'a, D, A> {
+        self.indent = indent;
+        self
+    }
+
+    pub fn to_doc(self, allocator: &'a D) -> DocBuilder<'a, D, A>
+    where
+        D::Doc: Clone,
+        A: Clone,
+    {
+        let mut widths = Vec::new();
+        let mut flat = true;
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                match flat_width(&cell.1) {
+                    Some(width) if i < widths.len() => widths[i] = cmp::max(widths[i], width),
+                    Some(width) => widths.push(width),
+                    None => flat = false,
+                }
+            }
+        }
+
+        let stacked = self.stacked(allocator);
+        if !flat {
+            return stacked;
+        }
+
+        let mut table = allocator.nil();
+        for (i, row) in self.rows.into_iter().enumerate() {
+            if i != 0 {
+                table = table.append(allocator.newline());
+            }
+            let last = row.len().saturating_sub(1);
+            for (j, cell) in row.into_iter().enumerate() {
+                if j != 0 {
+                    table = table.append(allocator.text(self.separator.clone()));
+                }
+                let padding = widths[j] - flat_width(&cell.1).unwrap_or(0);
+                let alignment = self.alignments.get(j).cloned().unwrap_or(Alignment::Left);
+                let (before, after) = match alignment {
+                    Alignment::Left => (0, padding),
+                    Alignment::Right => (padding, 0),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                };
+                table = table.append(spaces(allocator, before)).append(cell);
+                // Padding at the end of a line would only be trailing whitespace
+                if j != last {
+                    table = table.append(spaces(allocator, after));
+                }
+            }
+        }
+        table.if_break(stacked).group()
+    }
+
+    /// Lays out each row on its own lines, with every cell after the first one on a new line.
+    fn stacked(&self, allocator: &'a D) -> DocBuilder<'a, D, A>
+    where
+        D::Doc: Clone,
+        A: Clone,
+    {
+        let mut doc = allocator.nil();
+        for (i, row) in self.rows.iter().enumerate() {
+            if i != 0 {
+                doc = doc.append(allocator.newline());
+            }
+            let mut cells = row.iter().map(|cell| DocBuilder(allocator, cell.1.clone()));
+            if let Some(first) = cells.next() {
+                let rest = allocator.concat(cells.map(|cell| allocator.newline().append(cell)));
+                doc = doc.append(first).append(rest.nest(self.indent));
+            }
+        }
+        doc
+    }
+}
+
+fn spaces<'a, D, A>(allocator: &'a D, spaces: usize) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    if spaces == 0 {
+        allocator.nil()
+    } else {
+        allocator.text(" ".repeat(spaces))
+    }
+}
@@ -0,0 +1,108 @@
+//! Construction of documents from format string like templates.
+//!
+//! See [`format_doc!`](../macro.format_doc.html) for a macro which builds a `Template`.
+
+use {Doc, DocAllocator, DocBuilder};
+
+/// A template where each `{}` is replaced by the next argument and each run of whitespace becomes
+/// a `space()`, breaking into a new line if the enclosing group does not fit.
+///
+/// `{{` and `}}` insert literal braces.
+///
+/// ```
+/// use pretty::template::Template;
+/// use pretty::{Arena, DocAllocator};
+///
+/// let arena = Arena::<()>::new();
+/// let doc = Template::new(&arena, "fn {}({}) -> {}")
+///     .arg("main")
+///     .arg(arena.nil())
+///     .arg("()")
+///     .build()
+///     .group();
+///
+/// assert_eq!(doc.1.pretty(80).to_string(), "fn main() -> ()");
+/// assert_eq!(doc.1.pretty(10).to_string(), "fn\nmain()\n->\n()");
+/// ```
+pub struct Template<'a, D, A = ()>
+where
+    D: ?Sized + DocAllocator<'a, A> + 'a,
+{
+    allocator: &'a D,
+    template: &'a str,
+    args: Vec<Doc<'a, D::Doc, A>>,
+}
+
+impl<'a, D, A> Template<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    pub fn new(allocator: &'a D, template: &'a str) -> Template<'a, D, A> {
+        Template {
+            allocator,
+            template,
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds the document for the next `{}` placeholder.
+    pub fn arg<E>(mut self, arg: E) -> Template<'a, D, A>
+    where
+        E: Into<Doc<'a, D::Doc, A>>,
+    {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Builds the document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of placeholders does not match the number of arguments or if the
+    /// template contains an unmatched brace.
+    pub fn build(self) -> DocBuilder<'a, D, A> {
+        let Template {
+            allocator,
+            template,
+            args,
+        } = self;
+        let mut args = args.into_iter();
+        let mut doc = allocator.nil();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            let len = rest
+                .find(|c: char| c == '{' || c == '}' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            if len != 0 {
+                doc = doc.append(allocator.text(&rest[..len]));
+                rest = &rest[len..];
+                continue;
+            }
+
+            if rest.starts_with("{{") {
+                doc = doc.append(allocator.text("{"));
+                rest = &rest[2..];
+            } else if rest.starts_with("}}") {
+                doc = doc.append(allocator.text("}"));
+                rest = &rest[2..];
+            } else if rest.starts_with("{}") {
+                let arg = args
+                    .next()
+                    .unwrap_or_else(|| panic!("missing argument for template `{}`", template));
+                doc = doc.append(arg);
+                rest = &rest[2..];
+            } else if rest.starts_with(|c: char| c.is_whitespace()) {
+                doc = doc.append(allocator.space());
+                rest = rest.trim_start();
+            } else {
+                panic!("unmatched brace in template `{}`", template);
+            }
+        }
+
+        if args.next().is_some() {
+            panic!("too many arguments for template `{}`", template);
+        }
+        doc
+    }
+}
@@ -0,0 +1,185 @@
+//! Drawing of trees with box-drawing connectors, in the style of `cargo tree` or `tree`.
+//!
+//! Each node is put on its own line, below its parent and behind the connectors leading to it.
+//! Labels are arbitrary documents, so they can be annotated or span several lines, in which case
+//! the following lines are aligned with the first one.
+//!
+//! ```
+//! use pretty::tree::{Printer, Tree};
+//! use pretty::{BoxAllocator, DocAllocator};
+//!
+//! let a = &BoxAllocator;
+//! let tree = Tree::new(a.text("pretty"))
+//!     .child(
+//!         Tree::new(a.text("src"))
+//!             .child(Tree::new(a.text("lib.rs")))
+//!             .child(Tree::new(a.text("render.rs"))),
+//!     )
+//!     .child(Tree::new(a.text("Cargo.toml")));
+//! let doc = Printer::<()>::new().to_doc(a, tree).1;
+//!
+//! assert_eq!(
+//!     doc.pretty(80).to_string(),
+//!     "pretty
+//! ├── src
+//! │   ├── lib.rs
+//! │   └── render.rs
+//! └── Cargo.toml"
+//! );
+//! ```
+
+use render::text_width;
+use {DocAllocator, DocBuilder};
+
+/// A node with its label and children.
+pub struct Tree<'a, D, A = ()>
+where
+    D: ?Sized + DocAllocator<'a, A> + 'a,
+{
+    pub label: DocBuilder<'a, D, A>,
+    pub children: Vec<Tree<'a, D, A>>,
+}
+
+impl<'a, D, A> Tree<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    /// Creates a node without children.
+    pub fn new(label: DocBuilder<'a, D, A>) -> Tree<'a, D, A> {
+        Tree {
+            label,
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds a child to the node.
+    pub fn child(mut self, child: Tree<'a, D, A>) -> Tree<'a, D, A> {
+        self.children.push(child);
+        self
+    }
+}
+
+/// The kinds of tokens which can be annotated.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Kind {
+    /// The connectors in front of a label, including the lines passing by it.
+    Connector,
+    /// The labels, around any annotations they already have.
+    Label,
+    /// The marker for the children of a node which are not shown.
+    Folded,
+}
+
+/// Converts trees into documents.
+pub struct Printer<A = ()> {
+    ascii: bool,
+    depth: Option<usize>,
+    annotate: Option<Box<dyn Fn(Kind) -> A>>,
+}
+
+impl<A> Default for Printer<A> {
+    fn default() -> Printer<A> {
+        Printer::new()
+    }
+}
+
+impl<A> Printer<A> {
+    /// Creates a printer which draws connectors with box-drawing characters, shows every node
+    /// and does not annotate anything.
+    pub fn new() -> Printer<A> {
+        Printer {
+            ascii: false,
+            depth: None,
+            annotate: None,
+        }
+    }
+
+    /// Draws the connectors with `|`, `` ` `` and `-` instead of box-drawing characters.
+    pub fn ascii(mut self) -> Printer<A> {
+        self.ascii = true;
+        self
+    }
+
+    /// Only shows the nodes up to `depth` levels below the root. Nodes whose children are not
+    /// shown are followed by ` …`.
+    pub fn depth(mut self, depth: usize) -> Printer<A> {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Annotates each token with the annotation returned by `annotate` for its kind.
+    pub fn annotate<F>(mut self, annotate: F) -> Printer<A>
+    where
+        F: Fn(Kind) -> A + 'static,
+    {
+        self.annotate = Some(Box::new(annotate));
+        self
+    }
+
+    pub fn to_doc<'a, D>(&self, allocator: &'a D, tree: Tree<'a, D, A>) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        self.node(allocator, String::new(), tree, 0).align()
+    }
+
+    /// Lays out the label of `tree` followed by its children, which are put behind `prefix`. The
+    /// label is indented by the width of `prefix` since that is where it starts.
+    fn node<'a, D>(
+        &self,
+        allocator: &'a D,
+        prefix: String,
+        tree: Tree<'a, D, A>,
+        depth: usize,
+    ) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let mut doc = self
+            .token(Kind::Label, tree.label)
+            .nest(text_width(&prefix));
+        if tree.children.is_empty() {
+            return doc;
+        }
+        if self.depth.map_or(false, |max| depth >= max) {
+            return doc.append(self.token(Kind::Folded, allocator.text(" …")));
+        }
+
+        let (branch, last_branch, line, space) = if self.ascii {
+            ("|-- ", "`-- ", "|   ", "    ")
+        } else {
+            ("├── ", "└── ", "│   ", "    ")
+        };
+        let last = tree.children.len() - 1;
+        for (i, child) in tree.children.into_iter().enumerate() {
+            let (connector, continuation) = if i == last {
+                (last_branch, space)
+            } else {
+                (branch, line)
+            };
+            doc = doc
+                .append(allocator.newline())
+                .append(self.token(
+                    Kind::Connector,
+                    allocator.text(format!("{}{}", prefix, connector)),
+                ))
+                .append(self.node(
+                    allocator,
+                    format!("{}{}", prefix, continuation),
+                    child,
+                    depth + 1,
+                ));
+        }
+        doc
+    }
+
+    fn token<'a, D>(&self, kind: Kind, doc: DocBuilder<'a, D, A>) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match self.annotate {
+            Some(ref annotate) => doc.annotate(annotate(kind)),
+            None => doc,
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Conversion of values into documents.
+//!
+//! The [`Pretty`](trait.Pretty.html) trait turns a value into a document using a
+//! [`DocAllocator`](../trait.DocAllocator.html). It is implemented for the common types of the
+//! standard library, which are laid out like their `Debug` output: on a single line if they fit,
+//! and otherwise with one element per line, indented and followed by a trailing comma.
+//!
+//! ```
+//! use pretty::value::Pretty;
+//! use pretty::{BoxAllocator, BoxDoc, Doc};
+//!
+//! let value = vec![(1, "one"), (2, "two")];
+//! let doc: Doc<BoxDoc<()>> = value.pretty(&BoxAllocator).into();
+//! assert_eq!(doc.pretty(80).to_string(), r#"[(1, "one"), (2, "two")]"#);
+//! assert_eq!(
+//!     doc.pretty(20).to_string(),
+//!     "[\n    (1, \"one\"),\n    (2, \"two\"),\n]"
+//! );
+//! ```
+//!
+//! The layout functions of this module can be used to implement `Pretty` for other types.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use {Doc, DocAllocator, DocBuilder};
+
+/// The indentation used for the elements of broken lists and records.
+pub const INDENT: usize = 4;
+
+/// Conversion of a value into a document.
+pub trait Pretty<'a, D, A = ()>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A>;
+}
+
+/// Lays out `docs` separated by commas between `open` and `close`, like `[1, 2, 3]`.
+///
+/// If the elements do not fit on one line each element is placed on its own line instead.
+pub fn delimited<'a, D, A, I>(
+    allocator: &'a D,
+    open: &'a str,
+    docs: I,
+    close: &'a str,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator,
+    I::Item: Into<Doc<'a, D::Doc, A>>,
+{
+    block(allocator, open, docs, close, INDENT, false, true)
+}
+
+/// Lays out a value with named fields, like `Point { x: 1, y: 2 }`.
+pub fn record<'a, D, A, I, S>(allocator: &'a D, name: &'a str, fields: I) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = (S, DocBuilder<'a, D, A>)>,
+    S: Into<Doc<'a, D::Doc, A>>,
+{
+    let mut fields = fields.into_iter().peekable();
+    if fields.peek().is_none() {
+        return allocator.text(name);
+    }
+    let fields = fields.map(|(name, value)| field(allocator, name, value));
+    let body = block(allocator, "{", fields, "}", INDENT, true, true);
+    if name.is_empty() {
+        body
+    } else {
+        allocator
+            .text(name)
+            .append(allocator.text(" "))
+            .append(body)
+    }
+}
+
+/// Lays out a value with positional fields, like `Some(1)` or `(1, 2)` when `name` is empty.
+pub fn tuple<'a, D, A, I>(allocator: &'a D, name: &'a str, docs: I) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator,
+    I::Item: Into<Doc<'a, D::Doc, A>>,
+{
+    allocator
+        .text(name)
+        .append(delimited(allocator, "(", docs, ")"))
+}
+
+/// Lays out a map from keys to values, like `{"a": 1, "b": 2}`.
+pub fn map<'a, D, A, I>(allocator: &'a D, entries: I) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator<Item = (DocBuilder<'a, D, A>, DocBuilder<'a, D, A>)>,
+{
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| field(allocator, key, value));
+    delimited(allocator, "{", entries, "}")
+}
+
+fn field<'a, D, A, S>(
+    allocator: &'a D,
+    name: S,
+    value: DocBuilder<'a, D, A>,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    S: Into<Doc<'a, D::Doc, A>>,
+{
+    allocator
+        .nil()
+        .append(name)
+        .append(allocator.text(": "))
+        .append(value)
+}
+
+pub(crate) fn block<'a, D, A, I>(
+    allocator: &'a D,
+    open: &'a str,
+    docs: I,
+    close: &'a str,
+    indent: usize,
+    padded: bool,
+    trailing_comma: bool,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    I: IntoIterator,
+    I::Item: Into<Doc<'a, D::Doc, A>>,
+{
+    // A line break which is a space when `padded` and nothing otherwise if the group fits
+    let line = || {
+        if padded {
+            allocator.space()
+        } else {
+            allocator.nil().if_break(allocator.space())
+        }
+    };
+
+    let mut docs = docs.into_iter();
+    let first = match docs.next() {
+        Some(first) => first,
+        None => return allocator.text(open).append(allocator.text(close)),
+    };
+    let mut body = line().append(first);
+    for doc in docs {
+        body = body
+            .append(allocator.text(","))
+            .append(allocator.space())
+            .append(doc);
+    }
+    if trailing_comma {
+        body = body.append(allocator.nil().if_break(allocator.text(",")));
+    }
+
+    allocator
+        .text(open)
+        .append(body.nest(indent))
+        .append(line())
+        .append(allocator.text(close))
+        .group()
+        .foldable(
+            allocator
+                .text(open)
+                .append(allocator.text("…"))
+                .append(allocator.text(close)),
+        )
+}
+
+macro_rules! impl_display {
+    ($($ty:ty),*) => {$(
+        impl<'a, D, A> Pretty<'a, D, A> for $ty
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                allocator.as_string(self)
+            }
+        }
+    )*};
+}
+
+impl_display! {
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool
+}
+
+macro_rules! impl_debug {
+    ($($ty:ty),*) => {$(
+        impl<'a, D, A> Pretty<'a, D, A> for $ty
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                allocator.text(format!("{:?}", self))
+            }
+        }
+    )*};
+}
+
+impl_debug! { char, str, String, () }
+
+macro_rules! impl_deref {
+    ($($ty:ty),*) => {$(
+        impl<'a, 'b, D, A, T> Pretty<'a, D, A> for $ty
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+            T: ?Sized + Pretty<'a, D, A>,
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                (**self).pretty(allocator)
+            }
+        }
+    )*};
+}
+
+impl_deref! { &'b T, &'b mut T, Box<T>, Rc<T>, Arc<T> }
+
+impl<'a, D, A, T> Pretty<'a, D, A> for Option<T>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: Pretty<'a, D, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        match *self {
+            Some(ref value) => tuple(allocator, "Some", Some(value.pretty(allocator))),
+            None => allocator.text("None"),
+        }
+    }
+}
+
+impl<'a, D, A, T, E> Pretty<'a, D, A> for Result<T, E>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: Pretty<'a, D, A>,
+    E: Pretty<'a, D, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        match *self {
+            Ok(ref value) => tuple(allocator, "Ok", Some(value.pretty(allocator))),
+            Err(ref err) => tuple(allocator, "Err", Some(err.pretty(allocator))),
+        }
+    }
+}
+
+macro_rules! impl_seq {
+    ($open:expr, $close:expr; $($ty:ty),*) => {$(
+        impl<'a, D, A, T> Pretty<'a, D, A> for $ty
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+            T: Pretty<'a, D, A>,
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                let docs = self.iter().map(|value| value.pretty(allocator));
+                delimited(allocator, $open, docs, $close)
+            }
+        }
+    )*};
+}
+
+impl_seq! { "[", "]"; [T], Vec<T>, VecDeque<T> }
+impl_seq! { "{", "}"; BTreeSet<T> }
+
+impl<'a, D, A, T, const N: usize> Pretty<'a, D, A> for [T; N]
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: Pretty<'a, D, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        self[..].pretty(allocator)
+    }
+}
+
+impl<'a, D, A, T, S> Pretty<'a, D, A> for HashSet<T, S>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T: Pretty<'a, D, A> + Eq + Hash,
+    S: BuildHasher,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        let docs = self.iter().map(|value| value.pretty(allocator));
+        delimited(allocator, "{", docs, "}")
+    }
+}
+
+impl<'a, D, A, K, V> Pretty<'a, D, A> for BTreeMap<K, V>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    K: Pretty<'a, D, A>,
+    V: Pretty<'a, D, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        map(
+            allocator,
+            self.iter()
+                .map(|(key, value)| (key.pretty(allocator), value.pretty(allocator))),
+        )
+    }
+}
+
+impl<'a, D, A, K, V, S> Pretty<'a, D, A> for HashMap<K, V, S>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    K: Pretty<'a, D, A> + Eq + Hash,
+    V: Pretty<'a, D, A>,
+    S: BuildHasher,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        map(
+            allocator,
+            self.iter()
+                .map(|(key, value)| (key.pretty(allocator), value.pretty(allocator))),
+        )
+    }
+}
+
+impl<'a, D, A, T0> Pretty<'a, D, A> for (T0,)
+where
+    D: ?Sized + DocAllocator<'a, A>,
+    T0: Pretty<'a, D, A>,
+{
+    fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        allocator
+            .text("(")
+            .append(self.0.pretty(allocator))
+            .append(allocator.text(",)"))
+    }
+}
+
+macro_rules! impl_tuple {
+    ($(($($name:ident . $index:tt),*))*) => {$(
+        impl<'a, D, A, $($name),*> Pretty<'a, D, A> for ($($name,)*)
+        where
+            D: ?Sized + DocAllocator<'a, A>,
+            $($name: Pretty<'a, D, A>,)*
+        {
+            fn pretty(&self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+                tuple(allocator, "", vec![$(self.$index.pretty(allocator)),*])
+            }
+        }
+    )*};
+}
+
+impl_tuple! {
+    (T0.0, T1.1)
+    (T0.0, T1.1, T2.2)
+    (T0.0, T1.1, T2.2, T3.3)
+    (T0.0, T1.1, T2.2, T3.3, T4.4)
+    (T0.0, T1.1, T2.2, T3.3, T4.4, T5.5)
+    (T0.0, T1.1, T2.2, T3.3, T4.4, T5.5, T6.6)
+    (T0.0, T1.1, T2.2, T3.3, T4.4, T5.5, T6.6, T7.7)
+}
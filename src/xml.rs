@@ -0,0 +1,416 @@
+//! Pretty printing of XML and HTML element trees.
+//!
+//! Elements which only contain other elements are put on one line when they fit and otherwise get
+//! one child per line. In elements containing text the text is wrapped at its whitespace, while
+//! whitespace significant elements such as `<pre>` or those with `xml:space="preserve"` are
+//! printed exactly as they are. Attribute lists are broken over several lines when they do not fit.
+//!
+//! ```
+//! use pretty::xml::{Element, Printer};
+//! use pretty::BoxAllocator;
+//!
+//! let page = Element::new("body")
+//!     .child(Element::new("h1").attr("class", "title").child("Hello"))
+//!     .child(Element::new("p").child("A short paragraph of text with a ").child(
+//!         Element::new("a").attr("href", "https://example.com").child("link"),
+//!     ))
+//!     .into();
+//! let printer = Printer::<()>::new();
+//! let doc = printer.to_doc(&BoxAllocator, &page).1;
+//!
+//! assert_eq!(
+//!     doc.pretty(50).to_string(),
+//!     r#"<body>
+//!   <h1 class="title">Hello</h1>
+//!   <p>
+//!     A short paragraph of text with a
+//!     <a href="https://example.com">link</a>
+//!   </p>
+//! </body>"#
+//! );
+//! ```
+//!
+//! Tags, attribute names, attribute values and comments can be annotated, for instance to color
+//! them with `render_colored`.
+
+use std::collections::HashSet;
+
+use {DocAllocator, DocBuilder};
+
+/// A node of an element tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+    /// A comment, which is written without escaping since XML has no escapes in comments. It must
+    /// not contain `--` or end with `-` for the output to be valid XML.
+    Comment(String),
+}
+
+impl From<Element> for Node {
+    fn from(element: Element) -> Node {
+        Node::Element(element)
+    }
+}
+
+impl<'s> From<&'s str> for Node {
+    fn from(text: &'s str) -> Node {
+        Node::Text(text.to_string())
+    }
+}
+
+impl From<String> for Node {
+    fn from(text: String) -> Node {
+        Node::Text(text)
+    }
+}
+
+/// An element with its attributes, in the order they are printed in, and its children.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// Creates an element without attributes and children.
+    pub fn new<S>(name: S) -> Element
+    where
+        S: Into<String>,
+    {
+        Element {
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds an attribute to the element.
+    pub fn attr<K, V>(mut self, name: K, value: V) -> Element
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds a child to the element.
+    pub fn child<N>(mut self, child: N) -> Element
+    where
+        N: Into<Node>,
+    {
+        self.children.push(child.into());
+        self
+    }
+}
+
+/// The kinds of tokens which can be annotated.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Kind {
+    /// The opening and closing tags, without their attributes.
+    Tag,
+    /// The names of attributes.
+    Attribute,
+    /// The values of attributes.
+    Value,
+    Comment,
+}
+
+/// Converts element trees into documents.
+pub struct Printer<A = ()> {
+    indent: usize,
+    preserve: HashSet<String>,
+    annotate: Option<Box<dyn Fn(Kind) -> A>>,
+}
+
+impl<A> Default for Printer<A> {
+    fn default() -> Printer<A> {
+        Printer::new()
+    }
+}
+
+impl<A> Printer<A> {
+    /// Creates a printer which indents by 2 spaces, preserves the whitespace in `pre`, `textarea`,
+    /// `script` and `style` elements and does not annotate anything.
+    pub fn new() -> Printer<A> {
+        Printer {
+            indent: 2,
+            preserve: ["pre", "textarea", "script", "style"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            annotate: None,
+        }
+    }
+
+    /// Sets the number of spaces the children of broken elements are indented by.
+    pub fn indent(mut self, indent: usize) -> Printer<A> {
+        self.indent = indent;
+        self
+    }
+
+    /// Prints the content of elements named `name` exactly as it is.
+    pub fn preserve<S>(mut self, name: S) -> Printer<A>
+    where
+        S: Into<String>,
+    {
+        self.preserve.insert(name.into());
+        self
+    }
+
+    /// Annotates each token with the annotation returned by `annotate` for its kind.
+    pub fn annotate<F>(mut self, annotate: F) -> Printer<A>
+    where
+        F: Fn(Kind) -> A + 'static,
+    {
+        self.annotate = Some(Box::new(annotate));
+        self
+    }
+
+    pub fn to_doc<'a, D>(&self, allocator: &'a D, node: &'a Node) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match *node {
+            Node::Element(ref element) => self.element(allocator, element),
+            Node::Text(ref text) => self.text(allocator, text),
+            Node::Comment(ref comment) => self.comment(allocator, comment),
+        }
+    }
+
+    fn element<'a, D>(&self, allocator: &'a D, element: &'a Element) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        if self.is_preserved(element) {
+            return self.verbatim(allocator, element);
+        }
+
+        let children = element
+            .children
+            .iter()
+            .filter(|child| !is_blank(child))
+            .collect::<Vec<_>>();
+        if children.is_empty() {
+            return self.open_tag(allocator, element, true);
+        }
+
+        let mixed = children
+            .iter()
+            .any(|child| matches!(**child, Node::Text(_)));
+        let body = if mixed {
+            // Whitespace between text and elements is significant, so children are only
+            // separated where the text has whitespace. Whitespace at the start and end is
+            // replaced by the line breaks around the body.
+            let last = element.children.len() - 1;
+            let mut pieces = Vec::new();
+            for (i, child) in element.children.iter().enumerate() {
+                match *child {
+                    Node::Text(ref text) => {
+                        let mut text = &text[..];
+                        if i == 0 {
+                            text = text.trim_start();
+                        }
+                        if i == last {
+                            text = text.trim_end();
+                        }
+                        words(allocator, text, &mut pieces);
+                    }
+                    _ => pieces.push(Some(self.to_doc(allocator, child))),
+                }
+            }
+            fill(allocator, pieces)
+        } else {
+            let mut body = allocator.nil();
+            for (i, child) in children.into_iter().enumerate() {
+                if i != 0 {
+                    body = body.append(softline(allocator));
+                }
+                body = body.append(self.to_doc(allocator, child));
+            }
+            body
+        };
+
+        self.open_tag(allocator, element, false)
+            .append(softline(allocator).append(body).nest(self.indent))
+            .append(softline(allocator))
+            .append(self.close_tag(allocator, element))
+            .group()
+    }
+
+    /// Lays out `<name attributes>`, or `<name attributes/>` if `empty`.
+    fn open_tag<'a, D>(
+        &self,
+        allocator: &'a D,
+        element: &'a Element,
+        empty: bool,
+    ) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let mut attributes = allocator.nil();
+        for (name, value) in &element.attributes {
+            attributes = attributes
+                .append(allocator.space())
+                .append(self.attribute(allocator, name, value));
+        }
+        let close = if empty { "/>" } else { ">" };
+        self.token(
+            Kind::Tag,
+            allocator
+                .text("<")
+                .append(allocator.text(&element.name[..])),
+        )
+        .append(attributes.nest(self.indent).group())
+        .append(self.token(Kind::Tag, allocator.text(close)))
+    }
+
+    fn close_tag<'a, D>(&self, allocator: &'a D, element: &'a Element) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        self.token(
+            Kind::Tag,
+            allocator
+                .text("</")
+                .append(allocator.text(&element.name[..]))
+                .append(allocator.text(">")),
+        )
+    }
+
+    fn attribute<'a, D>(&self, allocator: &'a D, name: &'a str, value: &str) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        self.token(Kind::Attribute, allocator.text(name))
+            .append(allocator.text("="))
+            .append(self.token(
+                Kind::Value,
+                allocator.text(format!("\"{}\"", escape(value, true))),
+            ))
+    }
+
+    fn text<'a, D>(&self, allocator: &'a D, text: &str) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        let mut pieces = Vec::new();
+        words(allocator, text, &mut pieces);
+        fill(allocator, pieces)
+    }
+
+    fn comment<'a, D>(&self, allocator: &'a D, comment: &str) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        self.token(Kind::Comment, allocator.text(format!("<!--{}-->", comment)))
+    }
+
+    /// Lays out an element and its content exactly as they are.
+    fn verbatim<'a, D>(&self, allocator: &'a D, element: &'a Element) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        if element.children.is_empty() {
+            return self.open_tag(allocator, element, true);
+        }
+        let mut doc = self.open_tag(allocator, element, false);
+        for child in &element.children {
+            doc = doc.append(match *child {
+                Node::Element(ref element) => self.verbatim(allocator, element),
+                Node::Text(ref text) => allocator.text(escape(text, false)),
+                Node::Comment(ref comment) => self.comment(allocator, comment),
+            });
+        }
+        doc.append(self.close_tag(allocator, element))
+    }
+
+    fn is_preserved(&self, element: &Element) -> bool {
+        self.preserve.contains(&element.name)
+            || element
+                .attributes
+                .iter()
+                .any(|(name, value)| name == "xml:space" && value == "preserve")
+    }
+
+    fn token<'a, D>(&self, kind: Kind, doc: DocBuilder<'a, D, A>) -> DocBuilder<'a, D, A>
+    where
+        D: ?Sized + DocAllocator<'a, A>,
+    {
+        match self.annotate {
+            Some(ref annotate) => doc.annotate(annotate(kind)),
+            None => doc,
+        }
+    }
+}
+
+fn softline<'a, D, A>(allocator: &'a D) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    allocator.nil().if_break(allocator.space())
+}
+
+/// Splits `text` into its words, pushing `None` for each run of whitespace.
+fn words<'a, D, A>(allocator: &'a D, text: &str, pieces: &mut Vec<Option<DocBuilder<'a, D, A>>>)
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    for (i, word) in text.split(char::is_whitespace).enumerate() {
+        if i != 0 && !pieces.last().map_or(false, Option::is_none) {
+            pieces.push(None);
+        }
+        if !word.is_empty() {
+            pieces.push(Some(allocator.text(escape(word, false))));
+        }
+    }
+}
+
+/// Lays out words and elements with each `None` as a space which is broken if what follows it
+/// does not fit on the line.
+fn fill<'a, D, A>(
+    allocator: &'a D,
+    pieces: Vec<Option<DocBuilder<'a, D, A>>>,
+) -> DocBuilder<'a, D, A>
+where
+    D: ?Sized + DocAllocator<'a, A>,
+{
+    let mut doc = allocator.nil();
+    let mut pieces = pieces.into_iter();
+    while let Some(piece) = pieces.next() {
+        doc = doc.append(match piece {
+            Some(piece) => piece,
+            // Grouping the space with what follows measures all of it, an element included
+            None => match pieces.next() {
+                Some(Some(next)) => allocator.space().append(next).group(),
+                _ => allocator.space().group(),
+            },
+        });
+    }
+    doc
+}
+
+fn is_blank(node: &Node) -> bool {
+    match *node {
+        Node::Text(ref text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Escapes the characters of `text` which can not appear in text, or in attribute values if
+/// `attribute` is set.
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}